@@ -0,0 +1,34 @@
+use anstml::Parser;
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+#[path = "../src/test_support/log.rs"]
+mod log;
+
+fn parse(criterion: &mut Criterion) {
+    let log = log::log(2000);
+    let mut group = criterion.benchmark_group("parse");
+    group.throughput(Throughput::Bytes(log.len() as u64));
+    group.bench_function("parse_str", |bencher| {
+        bencher.iter(|| Parser::default().parse_str(black_box(&log)).unwrap())
+    });
+    group.bench_function("parse_bytes", |bencher| {
+        bencher.iter(|| {
+            Parser::default()
+                .parse_bytes(black_box(log.as_bytes()))
+                .unwrap()
+        })
+    });
+    group.bench_function("parse_ansi_text", |bencher| {
+        bencher.iter(|| {
+            let mut parser = Parser::default();
+            parser
+                .parse_ansi_text(&mut black_box(&log).chars())
+                .unwrap();
+            parser
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -0,0 +1,112 @@
+#ifndef ANSTML_H
+#define ANSTML_H
+
+/* Generated with cbindgen from src/ffi.rs, don't edit by hand */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+typedef enum AnstmlStatus {
+  ANSTML_STATUS_OK = 0,
+  ANSTML_STATUS_NUMBER_PARSE,
+  ANSTML_STATUS_INVALID_START_BRACE,
+  ANSTML_STATUS_INVALID_FORMAT,
+  ANSTML_STATUS_TOO_LONG,
+  ANSTML_STATUS_ILLEGAL_COMMAND,
+  ANSTML_STATUS_INVALID_HTML,
+  ANSTML_STATUS_STALE_INDEX,
+  ANSTML_STATUS_ENCODE,
+  ANSTML_STATUS_NULL_POINTER,
+  /**
+   * A bug in the library, the converter it happened in can't be used any more.
+   */
+  ANSTML_STATUS_PANIC,
+} AnstmlStatus;
+
+/**
+ * Converts utf-8 input fed to it in pieces to html.
+ */
+typedef struct AnstmlConverter AnstmlConverter;
+
+/**
+ * The parser options, the same as the setters on the Rust `Parser`.
+ */
+typedef struct AnstmlOptions {
+  /**
+   * Turn `c\bc` and `_\bc` overstriking into bold and underlined text.
+   */
+  bool decode_overstrike;
+  /**
+   * Recognize the 8-bit C1 controls, like U+009B for CSI.
+   */
+  bool c1_controls;
+} AnstmlOptions;
+
+/**
+ * Html owned by the library, release it with `anstml_buffer_free`. It is not NUL terminated.
+ */
+typedef struct AnstmlBuffer {
+  uint8_t *data;
+  size_t len;
+} AnstmlBuffer;
+
+/**
+ * Creates a converter, `options` may be null for the defaults. Returns null if that fails.
+ *
+ * # Safety
+ * `options` must be null or point to valid options.
+ */
+struct AnstmlConverter *anstml_converter_new(const struct AnstmlOptions *options);
+
+/**
+ * Converts the next `len` bytes of input, writing the html for it to `out`.
+ *
+ * # Safety
+ * `converter` must come from `anstml_converter_new`, `data` must point to `len` readable bytes
+ * and `out` to writable memory for a buffer.
+ */
+enum AnstmlStatus anstml_converter_feed(struct AnstmlConverter *converter,
+                                        const uint8_t *data,
+                                        size_t len,
+                                        struct AnstmlBuffer *out);
+
+/**
+ * Writes the end of the html to `out` and frees the converter, also when conversion fails.
+ *
+ * # Safety
+ * `converter` must come from `anstml_converter_new` and can't be used afterwards, `out` must
+ * point to writable memory for a buffer.
+ */
+enum AnstmlStatus anstml_converter_finish(struct AnstmlConverter *converter,
+                                          struct AnstmlBuffer *out);
+
+/**
+ * Frees a converter without finishing it.
+ *
+ * # Safety
+ * `converter` must be null or come from `anstml_converter_new`, and can't be used afterwards.
+ */
+void anstml_converter_free(struct AnstmlConverter *converter);
+
+/**
+ * Converts `len` bytes of utf-8 input to html at once.
+ *
+ * # Safety
+ * `data` must point to `len` readable bytes, `options` must be null or point to valid options
+ * and `out` must point to writable memory for a buffer.
+ */
+enum AnstmlStatus anstml_convert(const uint8_t *data,
+                                 size_t len,
+                                 const struct AnstmlOptions *options,
+                                 struct AnstmlBuffer *out);
+
+/**
+ * Frees a buffer returned by the library.
+ *
+ * # Safety
+ * `buffer` must come from this library and can't be used afterwards.
+ */
+void anstml_buffer_free(struct AnstmlBuffer buffer);
+
+#endif  /* ANSTML_H */
@@ -0,0 +1,105 @@
+use std::io;
+
+use bytes::Bytes;
+use futures_util::{stream, Stream, StreamExt};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::{error::AnsiError, stream::HtmlConverter, Parser};
+
+const READ_SIZE: usize = 64 * 1024;
+
+fn invalid_data(error: AnsiError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", error))
+}
+
+// reads ansi text from `reader` and writes its html to `writer` as it arrives
+pub async fn convert_async<R, W>(parser: Parser, mut reader: R, mut writer: W) -> io::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut converter = HtmlConverter::new(parser);
+    let mut buffer = vec![0; READ_SIZE];
+    loop {
+        let read = reader.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        let html = converter.feed(&buffer[..read]).map_err(invalid_data)?;
+        writer.write_all(html.as_bytes()).await?;
+    }
+    let html = converter.finish().map_err(invalid_data)?;
+    writer.write_all(html.as_bytes()).await?;
+    writer.flush().await
+}
+
+// turns a stream of ansi text into a stream of html, like a response body for a log that is still being read
+pub fn html_stream<S>(parser: Parser, input: S) -> impl Stream<Item = io::Result<Bytes>>
+where
+    S: Stream<Item = io::Result<Bytes>> + Unpin,
+{
+    stream::unfold(
+        Some((HtmlConverter::new(parser), input)),
+        |state| async move {
+            let (mut converter, mut input) = state?;
+            match input.next().await {
+                Some(Ok(bytes)) => match converter.feed(&bytes) {
+                    Ok(html) => Some((Ok(Bytes::from(html)), Some((converter, input)))),
+                    Err(error) => Some((Err(invalid_data(error)), None)),
+                },
+                Some(Err(error)) => Some((Err(error), None)),
+                None => Some((
+                    converter.finish().map(Bytes::from).map_err(invalid_data),
+                    None,
+                )),
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::merged;
+
+    #[tokio::test]
+    async fn convert_reader_to_writer() {
+        let (mut sender, receiver) = tokio::io::duplex(7);
+        let writer = tokio::spawn(async move {
+            sender
+                .write_all("a \x1b[31mred \u{1f600}\x1b[0m <b>".as_bytes())
+                .await
+        });
+        let mut html = Vec::new();
+        convert_async(Parser::default(), receiver, &mut html)
+            .await
+            .unwrap();
+        writer.await.unwrap().unwrap();
+        let html = String::from_utf8(html).unwrap();
+        assert_eq!(
+            merged(html),
+            "<pre>a <span style=\"color:red;\">red \u{1f600}</span> &lt;b&gt;</pre>"
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_bytes() {
+        let pieces: [&[u8]; 4] = [b"\x1b[1", b"mbold\x1b[", b"0m \xc3", b"\xa9"];
+        let input = stream::iter(pieces.map(|piece| Ok(Bytes::copy_from_slice(piece))));
+        let html: Vec<_> = html_stream(Parser::default(), input).collect().await;
+        let html: Vec<_> = html.into_iter().map(|piece| piece.unwrap()).collect();
+        assert_eq!(
+            html.concat(),
+            b"<pre><span style=\"font-weight:bold;\">bold</span> \xc3\xa9</pre>"
+        );
+
+        let failing = stream::iter(vec![
+            Ok(Bytes::from_static(b"ok")),
+            Err(io::Error::other("gone")),
+            Ok(Bytes::from_static(b"never")),
+        ]);
+        let html: Vec<_> = html_stream(Parser::default(), failing).collect().await;
+        assert_eq!(html.len(), 2);
+        assert_eq!(html[1].as_ref().unwrap_err().to_string(), "gone");
+    }
+}
@@ -1,12 +1,21 @@
-#![recursion_limit = "512"]
-use std::io::{stderr, stdin, stdout, Error, Read, Write};
+use std::{
+    env,
+    io::{stderr, stdin, stdout, Error, Read, Write},
+};
 
-use anstml::{convert, error::AnsiError};
+use anstml::{
+    error::AnsiError, html_to_ansi, normalize, BbcodeRenderer, ColorDepth, DiscordRenderer,
+    IrcRenderer, LatexRenderer, PangoRenderer, Parser, QtRenderer, RtfRenderer, SemanticProfile,
+    SemanticRenderer, SvgRenderer,
+};
 
 #[derive(Debug)]
 enum AnsTmlError {
     AnsiError,
     IOError,
+    InvalidArgument,
+    #[cfg(feature = "serde")]
+    JsonError,
 }
 
 impl From<std::io::Error> for AnsTmlError {
@@ -15,6 +24,13 @@ impl From<std::io::Error> for AnsTmlError {
     }
 }
 
+#[cfg(feature = "serde")]
+impl From<serde_json::Error> for AnsTmlError {
+    fn from(_value: serde_json::Error) -> Self {
+        Self::JsonError
+    }
+}
+
 impl From<AnsiError> for AnsTmlError {
     fn from(_value: AnsiError) -> Self {
         Self::AnsiError
@@ -27,9 +43,188 @@ fn main() -> Result<(), AnsTmlError> {
     let amt = stdin.read_to_end(&mut ansi_buffer)?;
     writeln!(stderr(), "read {} bytes from stdin", amt)?;
 
-    let html = convert(&mut ansi_buffer.into_iter().map(|c| c as char))?;
+    let args: Vec<String> = env::args().collect();
+    let flag = |name: &str| args.iter().any(|arg| arg == name);
+    // the value of `--name=value` or `--name value`
+    let value = |name: &str| {
+        args.iter().enumerate().find_map(|(index, arg)| {
+            match arg.strip_prefix(name)?.strip_prefix('=') {
+                Some(value) => Some(value),
+                None if arg == name => args.get(index + 1).map(String::as_str),
+                None => None,
+            }
+        })
+    };
+
+    if flag("--from-html") {
+        write!(
+            stdout(),
+            "{}",
+            html_to_ansi(&String::from_utf8_lossy(&ansi_buffer))?
+        )?;
+        return Ok(());
+    }
+
+    if flag("--to-ansi") {
+        let depth = match value("--depth") {
+            None | Some("truecolor") => ColorDepth::TrueColor,
+            Some("256") => ColorDepth::Palette256,
+            Some("16") => ColorDepth::Palette16,
+            Some("none") => ColorDepth::None,
+            Some(depth) => {
+                writeln!(
+                    stderr(),
+                    "unknown depth {}, use truecolor, 256, 16 or none",
+                    depth
+                )?;
+                return Err(AnsTmlError::InvalidArgument);
+            }
+        };
+        let ansi = normalize(&mut String::from_utf8_lossy(&ansi_buffer).chars(), depth)?;
+        write!(stdout(), "{}", ansi)?;
+        return Ok(());
+    }
 
-    write!(stdout(), "{}", html)?;
+    let mut parser = Parser::default()
+        .decode_overstrike(flag("--overstrike"))
+        .c1_controls(flag("--c1"));
+    // every byte is a character without --utf8, so the continuation bytes of utf-8 input would
+    // be read as C1 controls
+    if flag("--c1")
+        && !flag("--utf8")
+        && !ansi_buffer.is_ascii()
+        && std::str::from_utf8(&ansi_buffer).is_ok()
+    {
+        writeln!(
+            stderr(),
+            "the input is utf-8, pass --utf8 to read C1 controls from it"
+        )?;
+        return Err(AnsTmlError::InvalidArgument);
+    }
+    if flag("--utf8") {
+        parser.parse_ansi_text(&mut String::from_utf8_lossy(&ansi_buffer).chars())?;
+    } else {
+        parser.parse_ansi_text(&mut ansi_buffer.into_iter().map(|c| c as char))?;
+    }
+    #[cfg(not(feature = "serde"))]
+    if value("--format").is_some() {
+        writeln!(
+            stderr(),
+            "--format needs anstml built with the serde feature"
+        )?;
+        return Err(AnsTmlError::InvalidArgument);
+    }
+    #[cfg(feature = "serde")]
+    if let Some(format) = value("--format") {
+        let runs = parser.take_chain().into_iter().map(anstml::StyledRun::from);
+        if format != "json" && format != "ndjson" {
+            writeln!(stderr(), "unknown format {}, use json or ndjson", format)?;
+            return Err(AnsTmlError::InvalidArgument);
+        }
+        if format == "ndjson" {
+            // a record per line of text, so the output can be split and indexed line by line
+            let mut stdout = stdout().lock();
+            for run in runs {
+                for line in run.text.split_inclusive('\n') {
+                    let record = anstml::StyledRun {
+                        text: line.to_string(),
+                        style: run.style.clone(),
+                    };
+                    serde_json::to_writer(&mut stdout, &record)?;
+                    writeln!(stdout)?;
+                }
+            }
+        } else {
+            serde_json::to_writer(stdout(), &runs.collect::<Vec<_>>())?;
+        }
+        return Ok(());
+    }
+    if flag("--strip") {
+        write!(stdout(), "{}", parser.plain_text())?;
+        return Ok(());
+    }
+    #[cfg(not(feature = "png"))]
+    if flag("--png") {
+        writeln!(stderr(), "--png needs anstml built with the png feature")?;
+        return Err(AnsTmlError::InvalidArgument);
+    }
+    #[cfg(feature = "png")]
+    if flag("--png") {
+        let png = anstml::PngRenderer::default().render(parser.ansi_chain())?;
+        stdout().write_all(&png)?;
+        return Ok(());
+    }
+    if flag("--svg") {
+        let svg = SvgRenderer::default()
+            .window_chrome(flag("--window"))
+            .render(parser.ansi_chain());
+        write!(stdout(), "{}", svg)?;
+        return Ok(());
+    }
+    if flag("--latex") {
+        let latex = LatexRenderer::default()
+            .standalone(true)
+            .render(parser.ansi_chain());
+        write!(stdout(), "{}", latex)?;
+        return Ok(());
+    }
+    if flag("--rtf") {
+        let rtf = RtfRenderer::default().render(parser.ansi_chain());
+        if flag("--with-html") {
+            // both formats as alternatives, for mail clients and clipboard tools that pick the richest one
+            let boundary = "anstml-alternative";
+            write!(
+                stdout(),
+                "MIME-Version: 1.0\r\nContent-Type: multipart/alternative; boundary=\"{0}\"\r\n\r\n\
+                --{0}\r\nContent-Type: text/html; charset=utf-8\r\n\r\n{1}\r\n\
+                --{0}\r\nContent-Type: text/rtf\r\n\r\n{2}\r\n--{0}--\r\n",
+                boundary,
+                parser.into_html(),
+                rtf
+            )?;
+        } else {
+            write!(stdout(), "{}", rtf)?;
+        }
+        return Ok(());
+    }
+    if let Some(profile) = value("--semantic") {
+        let profile = match profile {
+            "github" | "gitlab" => SemanticProfile::GitHub,
+            "discourse" => SemanticProfile::Discourse,
+            "permissive" => SemanticProfile::Permissive,
+            _ => {
+                writeln!(stderr(), "unknown semantic profile {}", profile)?;
+                return Err(AnsTmlError::InvalidArgument);
+            }
+        };
+        let html = SemanticRenderer::default()
+            .profile(profile)
+            .render(parser.ansi_chain());
+        write!(stdout(), "{}", html)?;
+        return Ok(());
+    }
+    let chat = if flag("--bbcode") {
+        Some(BbcodeRenderer::default().render(parser.ansi_chain()))
+    } else if flag("--irc") {
+        Some(
+            IrcRenderer::default()
+                .extended_colors(flag("--irc-99"))
+                .render(parser.ansi_chain()),
+        )
+    } else if flag("--discord") {
+        Some(DiscordRenderer::default().render(parser.ansi_chain()))
+    } else if flag("--pango") {
+        Some(PangoRenderer::default().render(parser.ansi_chain()))
+    } else if flag("--qt") {
+        Some(QtRenderer::default().render(parser.ansi_chain()))
+    } else {
+        None
+    };
+    if let Some(chat) = chat {
+        write!(stdout(), "{}", chat)?;
+        return Ok(());
+    }
+    parser.write_html_io(&mut stdout())?;
 
     Ok(())
 }
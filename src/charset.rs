@@ -0,0 +1,74 @@
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub(crate) enum Charset {
+    #[default]
+    Ascii,
+    DecSpecialGraphics,
+}
+
+impl Charset {
+    // the final character of the `ESC (` sequence that designates this set
+    #[cfg(feature = "std")]
+    pub(crate) fn designator(&self) -> char {
+        match self {
+            Charset::Ascii => 'B',
+            Charset::DecSpecialGraphics => '0',
+        }
+    }
+
+    pub(crate) fn translate(&self, c: char) -> char {
+        match self {
+            Charset::Ascii => c,
+            Charset::DecSpecialGraphics => match c {
+                '_' => '\u{00A0}',
+                '`' => '◆',
+                'a' => '▒',
+                'b' => '␉',
+                'c' => '␌',
+                'd' => '␍',
+                'e' => '␊',
+                'f' => '°',
+                'g' => '±',
+                'h' => '␤',
+                'i' => '␋',
+                'j' => '┘',
+                'k' => '┐',
+                'l' => '┌',
+                'm' => '└',
+                'n' => '┼',
+                'o' => '⎺',
+                'p' => '⎻',
+                'q' => '─',
+                'r' => '⎼',
+                's' => '⎽',
+                't' => '├',
+                'u' => '┤',
+                'v' => '┴',
+                'w' => '┬',
+                'x' => '│',
+                'y' => '≤',
+                'z' => '≥',
+                '{' => 'π',
+                '|' => '≠',
+                '}' => '£',
+                '~' => '·',
+                c => c,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(Charset::Ascii, "lqqkxAxmqqj", "lqqkxAxmqqj")]
+    #[case(Charset::DecSpecialGraphics, "lqqkxAxmqqj", "┌──┐│A│└──┘")]
+    #[case(Charset::DecSpecialGraphics, "tnu vw y|z", "├┼┤ ┴┬ ≤≠≥")]
+    #[case(Charset::DecSpecialGraphics, "ABC 123", "ABC 123")]
+    fn charset_translation(#[case] charset: Charset, #[case] text: &str, #[case] correct: &str) {
+        let translated: String = text.chars().map(|c| charset.translate(c)).collect();
+        assert_eq!(translated, correct);
+    }
+}
@@ -0,0 +1,290 @@
+use crate::{
+    color::{Color, ColorDepth},
+    state::{AnsiState, Intensity, InvertColors, Italics, StrikeThrough, Underline},
+    AnsiChain,
+};
+
+type Rgb = (u8, u8, u8);
+
+// BBCode has no background colors, faint, blinking or inverted text, those are dropped
+#[derive(Default)]
+pub struct BbcodeRenderer {}
+
+impl BbcodeRenderer {
+    pub fn render(&self, chain: &AnsiChain) -> String {
+        let mut bbcode = String::new();
+        for (state, text) in chain {
+            let mut tags = Vec::new();
+            if let Some(hex) = state.text_color.to_hex() {
+                tags.push(("color", format!("={}", hex)));
+            }
+            if state.intensity == Intensity::Bold {
+                tags.push(("b", String::new()));
+            }
+            if state.italic == Italics::Yes {
+                tags.push(("i", String::new()));
+            }
+            if state.underline != Underline::None {
+                tags.push(("u", String::new()));
+            }
+            if state.strikethrough == StrikeThrough::Yes {
+                tags.push(("s", String::new()));
+            }
+            for (tag, value) in &tags {
+                bbcode.push_str(&format!("[{}{}]", tag, value));
+            }
+            // BBCode has no escape character, so an opening bracket that could start a tag in
+            // the text gets a `[noparse]` of its own
+            bbcode.push_str(&text.replace('[', "[noparse][[/noparse]"));
+            for (tag, _) in tags.iter().rev() {
+                bbcode.push_str(&format!("[/{}]", tag));
+            }
+        }
+        bbcode
+    }
+}
+
+// the 16 classic mIRC colors followed by the extended colors 16 to 98
+const IRC_PALETTE: [u32; 99] = [
+    0xFFFFFF, 0x000000, 0x00007F, 0x009300, 0xFF0000, 0x7F0000, 0x9C009C, 0xFC7F00, 0xFFFF00,
+    0x00FC00, 0x009393, 0x00FFFF, 0x0000FC, 0xFF00FF, 0x7F7F7F, 0xD2D2D2, 0x470000, 0x472100,
+    0x474700, 0x324700, 0x004700, 0x00472C, 0x004747, 0x002747, 0x000047, 0x2E0047, 0x470047,
+    0x47002A, 0x740000, 0x743A00, 0x747400, 0x517400, 0x007400, 0x007449, 0x007474, 0x004074,
+    0x000074, 0x4B0074, 0x740074, 0x740045, 0xB50000, 0xB56300, 0xB5B500, 0x7DB500, 0x00B500,
+    0x00B571, 0x00B5B5, 0x0063B5, 0x0000B5, 0x7500B5, 0xB500B5, 0xB5006B, 0xFF0000, 0xFF8C00,
+    0xFFFF00, 0xB2FF00, 0x00FF00, 0x00FFA0, 0x00FFFF, 0x008CFF, 0x0000FF, 0xA500FF, 0xFF00FF,
+    0xFF0098, 0xFF5959, 0xFFB459, 0xFFFF71, 0xCFFF60, 0x6FFF6F, 0x65FFC9, 0x6DFFFF, 0x59B4FF,
+    0x5959FF, 0xC459FF, 0xFF66FF, 0xFF59BC, 0xFF9C9C, 0xFFD39C, 0xFFFF9C, 0xE2FF9C, 0x9CFF9C,
+    0x9CFFDB, 0x9CFFFF, 0x9CD3FF, 0x9C9CFF, 0xDC9CFF, 0xFF9CFF, 0xFF94D3, 0x000000, 0x131313,
+    0x282828, 0x363636, 0x4D4D4D, 0x656565, 0x818181, 0x9F9F9F, 0xBCBCBC, 0xE2E2E2, 0xFFFFFF,
+];
+
+// 99 is the client's default color
+const IRC_DEFAULT_COLOR: usize = 99;
+
+// IRC formatting ends with every message, so each line opens its formatting again
+#[derive(Default)]
+pub struct IrcRenderer {
+    extended_colors: bool,
+}
+
+impl IrcRenderer {
+    // use the 99 color palette instead of the 16 colors every client knows
+    pub fn extended_colors(mut self, extended_colors: bool) -> Self {
+        self.extended_colors = extended_colors;
+        self
+    }
+
+    pub fn render(&self, chain: &AnsiChain) -> String {
+        let mut irc = String::new();
+        let mut formatted = false;
+        for (state, text) in chain {
+            for (index, line) in text.split('\n').enumerate() {
+                if index > 0 {
+                    irc.push('\n');
+                    formatted = false;
+                }
+                if line.is_empty() {
+                    continue;
+                }
+                if formatted {
+                    irc.push('\x0f');
+                }
+                let codes = self.codes_of(state);
+                formatted = !codes.is_empty();
+                irc.push_str(&codes);
+                // a digit right after a color code would be read as part of it
+                if line.starts_with(|c: char| c.is_ascii_digit() || c == ',')
+                    && codes.ends_with(|c: char| c.is_ascii_digit())
+                {
+                    irc.push_str("\x02\x02");
+                }
+                irc.push_str(line);
+            }
+        }
+        if formatted {
+            irc.push('\x0f');
+        }
+        irc
+    }
+
+    fn codes_of(&self, state: &AnsiState) -> String {
+        let mut codes = String::new();
+        if state.intensity == Intensity::Bold {
+            codes.push('\x02');
+        }
+        if state.italic == Italics::Yes {
+            codes.push('\x1d');
+        }
+        if state.underline != Underline::None {
+            codes.push('\x1f');
+        }
+        if state.strikethrough == StrikeThrough::Yes {
+            codes.push('\x1e');
+        }
+        if state.invert_colors == InvertColors::Yes {
+            codes.push('\x16');
+        }
+        let foreground = self.nearest(&state.text_color);
+        let background = self.nearest(&state.background_color);
+        match (foreground, background) {
+            (None, None) => {}
+            (Some(foreground), None) => codes.push_str(&format!("\x03{:02}", foreground)),
+            (foreground, Some(background)) => codes.push_str(&format!(
+                "\x03{:02},{:02}",
+                foreground.unwrap_or(IRC_DEFAULT_COLOR),
+                background
+            )),
+        }
+        codes
+    }
+
+    fn nearest(&self, color: &Color) -> Option<usize> {
+        let (r, g, b) = color.to_rgb()?;
+        let palette = if self.extended_colors {
+            &IRC_PALETTE[..]
+        } else {
+            &IRC_PALETTE[..16]
+        };
+        let distance = |entry: &u32| {
+            let entry: Rgb = ((entry >> 16) as u8, (entry >> 8) as u8, *entry as u8);
+            [(r, entry.0), (g, entry.1), (b, entry.2)]
+                .into_iter()
+                .map(|(c, p)| (c as i32 - p as i32).pow(2))
+                .sum::<i32>()
+        };
+        (0..palette.len()).min_by_key(|&index| distance(&palette[index]))
+    }
+}
+
+// discord's `ansi` code blocks know bold, underline and the 8 basic colors, nothing else
+#[derive(Default)]
+pub struct DiscordRenderer {}
+
+impl DiscordRenderer {
+    pub fn render(&self, chain: &AnsiChain) -> String {
+        let mut discord = String::from("```ansi\n");
+        let mut previous = String::new();
+        for (state, text) in chain {
+            let codes = DiscordRenderer::codes_of(state);
+            if codes != previous {
+                discord.push_str(&format!("\x1b[{}m", codes));
+                previous = codes;
+            }
+            // a zero width space between backticks keeps them from closing the block
+            for c in text.chars() {
+                if c == '`' && discord.ends_with('`') {
+                    discord.push('\u{200B}');
+                }
+                discord.push(c);
+            }
+        }
+        if !discord.ends_with('\n') {
+            discord.push('\n');
+        }
+        discord.push_str("```");
+        discord
+    }
+
+    fn codes_of(state: &AnsiState) -> String {
+        let mut codes = vec!["0".to_string()];
+        if state.intensity == Intensity::Bold {
+            codes.push("1".to_string());
+        }
+        if state.underline != Underline::None {
+            codes.push("4".to_string());
+        }
+        for (color, base) in [(&state.text_color, 30), (&state.background_color, 40)] {
+            let index = match color.quantize(ColorDepth::Palette16) {
+                Color::None => continue,
+                Color::Byte(n) => n % 8,
+                Color::Black => 0,
+                Color::Red => 1,
+                Color::Green => 2,
+                Color::Yellow => 3,
+                Color::Blue => 4,
+                Color::Magenta => 5,
+                Color::Cyan => 6,
+                Color::White | Color::Full(..) => 7,
+            };
+            codes.push((base + index).to_string());
+        }
+        codes.join(";")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain() -> AnsiChain {
+        vec![
+            (AnsiState::default(), "build ".to_string()),
+            (AnsiState::from_code("[1;31m"), "failed\n2".to_string()),
+            (
+                AnsiState::from_code("[3;4;9;38;2;255;140;0;44m"),
+                " errors".to_string(),
+            ),
+            (AnsiState::from_code("[2;5m"), "```".to_string()),
+        ]
+    }
+
+    #[test]
+    fn render_bbcode() {
+        assert_eq!(
+            BbcodeRenderer::default().render(&chain()),
+            "build [color=#CD0000][b]failed\n2[/b][/color]\
+            [color=#FF8C00][i][u][s] errors[/s][/u][/i][/color]```"
+        );
+    }
+
+    #[test]
+    fn escape_brackets_in_bbcode() {
+        let chain = vec![
+            (AnsiState::default(), "[b]not bold[/b] ".to_string()),
+            (
+                AnsiState::from_code("[31m"),
+                "[ERROR] x[/noparse]".to_string(),
+            ),
+        ];
+        assert_eq!(
+            BbcodeRenderer::default().render(&chain),
+            "[noparse][[/noparse]b]not bold[noparse][[/noparse]/b] \
+            [color=#CD0000][noparse][[/noparse]ERROR] x[noparse][[/noparse]/noparse][/color]"
+        );
+    }
+
+    #[test]
+    fn render_irc() {
+        assert_eq!(
+            IrcRenderer::default().render(&chain()),
+            "build \x02\x0304failed\n\x02\x0304\x02\x022\x0f\x1d\x1f\x1e\x0307,12 errors\x0f```"
+        );
+        assert_eq!(
+            IrcRenderer::default()
+                .extended_colors(true)
+                .render(&chain()),
+            "build \x02\x0340failed\n\x02\x0340\x02\x022\x0f\x1d\x1f\x1e\x0353,12 errors\x0f```"
+        );
+    }
+
+    #[test]
+    fn render_discord() {
+        assert_eq!(
+            DiscordRenderer::default().render(&chain()),
+            "```ansi\n\x1b[0mbuild \x1b[0;1;31mfailed\n2\x1b[0;4;33;44m errors\x1b[0m`\u{200B}`\u{200B}`\n```"
+        );
+    }
+
+    #[test]
+    fn break_up_backtick_runs_in_discord() {
+        let chain = vec![
+            (AnsiState::default(), "a````b ``".to_string()),
+            (AnsiState::from_code("[1m"), "`c".to_string()),
+        ];
+        assert_eq!(
+            DiscordRenderer::default().render(&chain),
+            "```ansi\n\x1b[0ma`\u{200B}`\u{200B}`\u{200B}`b `\u{200B}`\x1b[0;1m`c\n```"
+        );
+    }
+}
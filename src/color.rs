@@ -1,9 +1,16 @@
-use std::fmt::Display;
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+use core::fmt::Display;
 
-use ansi_colours::rgb_from_ansi256;
+use ansi_colours::{ansi256_from_rgb, rgb_from_ansi256};
+
+#[cfg(feature = "std")]
+use crate::error::AnsiError;
 
 #[derive(Debug, PartialEq, Clone)]
-pub(crate) enum Color {
+pub enum Color {
     None,
     Byte(u8),
     Full(u8, u8, u8),
@@ -17,13 +24,22 @@ pub(crate) enum Color {
     White,
 }
 
+// the colors a terminal can show, used when re-encoding to ANSI codes
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ColorDepth {
+    TrueColor,
+    Palette256,
+    Palette16,
+    None,
+}
+
 impl Display for Color {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Color::None => f.write_str(""),
             Color::Byte(n) => {
                 let (r, g, b) = rgb_from_ansi256(*n);
-                write!(f, "#{:02X}{:02X}{:02X}", r, g, b)
+                Color::Full(r, g, b).fmt(f)
             }
             Color::Full(r, g, b) => write!(f, "#{:02X}{:02X}{:02X}", r, g, b),
             Color::Black => f.write_str("black"),
@@ -38,6 +54,121 @@ impl Display for Color {
     }
 }
 
+impl Color {
+    // the inverse of the `Display` implementation, 256 color palette entries come back as full colors
+    #[cfg(feature = "std")]
+    pub(crate) fn from_css(css: &str) -> Result<Color, AnsiError> {
+        match css {
+            "black" => Ok(Color::Black),
+            "red" => Ok(Color::Red),
+            "green" => Ok(Color::Green),
+            "yellow" => Ok(Color::Yellow),
+            "blue" => Ok(Color::Blue),
+            "magenta" => Ok(Color::Magenta),
+            "cyan" => Ok(Color::Cyan),
+            "white" => Ok(Color::White),
+            _ => {
+                let hex = css
+                    .strip_prefix('#')
+                    .filter(|hex| hex.len() == 6 && hex.is_ascii())
+                    .ok_or(AnsiError::InvalidHtml)?;
+                let component = |i: usize| {
+                    u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| AnsiError::InvalidHtml)
+                };
+                Ok(Color::Full(component(0)?, component(2)?, component(4)?))
+            }
+        }
+    }
+
+    // the SGR parameters selecting this color, `base` is 30 for text, 40 for background and 50 for underlines
+    pub(crate) fn to_sgr(&self, base: u8) -> String {
+        let named = |n: u8| {
+            // the named colors can't be used for underlines
+            if base == 50 {
+                format!("58;5;{}", n)
+            } else {
+                (base + n).to_string()
+            }
+        };
+        match self {
+            Color::None => (base + 9).to_string(),
+            // the bright colors have their own codes
+            Color::Byte(n @ 8..=15) if base != 50 => (base + 52 + n).to_string(),
+            Color::Byte(n) => format!("{};5;{}", base + 8, n),
+            Color::Full(r, g, b) => format!("{};2;{};{};{}", base + 8, r, g, b),
+            Color::Black => named(0),
+            Color::Red => named(1),
+            Color::Green => named(2),
+            Color::Yellow => named(3),
+            Color::Blue => named(4),
+            Color::Magenta => named(5),
+            Color::Cyan => named(6),
+            Color::White => named(7),
+        }
+    }
+
+    // the terminal's palette for the named and 256 colors, `None` for the default color
+    #[cfg(feature = "std")]
+    pub(crate) fn to_rgb(&self) -> Option<(u8, u8, u8)> {
+        match self {
+            Color::None => None,
+            Color::Byte(n) => Some(rgb_from_ansi256(*n)),
+            Color::Full(r, g, b) => Some((*r, *g, *b)),
+            Color::Black => Some(rgb_from_ansi256(0)),
+            Color::Red => Some(rgb_from_ansi256(1)),
+            Color::Green => Some(rgb_from_ansi256(2)),
+            Color::Yellow => Some(rgb_from_ansi256(3)),
+            Color::Blue => Some(rgb_from_ansi256(4)),
+            Color::Magenta => Some(rgb_from_ansi256(5)),
+            Color::Cyan => Some(rgb_from_ansi256(6)),
+            Color::White => Some(rgb_from_ansi256(7)),
+        }
+    }
+
+    // `#RRGGBB`, for the formats that take no color names
+    #[cfg(feature = "std")]
+    pub(crate) fn to_hex(&self) -> Option<String> {
+        self.to_rgb()
+            .map(|(r, g, b)| Color::Full(r, g, b).to_string())
+    }
+
+    pub(crate) fn quantize(&self, depth: ColorDepth) -> Color {
+        match (depth, self) {
+            (ColorDepth::None, _) => Color::None,
+            (ColorDepth::TrueColor, _) => self.clone(),
+            (ColorDepth::Palette256, Color::Full(r, g, b)) => {
+                Color::Byte(ansi256_from_rgb((*r, *g, *b)))
+            }
+            (ColorDepth::Palette256, _) => self.clone(),
+            (ColorDepth::Palette16, Color::Byte(0..=15) | Color::None) => self.clone(),
+            (ColorDepth::Palette16, Color::Byte(n)) => nearest_of_16(rgb_from_ansi256(*n)),
+            (ColorDepth::Palette16, Color::Full(r, g, b)) => nearest_of_16((*r, *g, *b)),
+            (ColorDepth::Palette16, _) => self.clone(),
+        }
+    }
+}
+
+fn nearest_of_16((r, g, b): (u8, u8, u8)) -> Color {
+    let distance = |n: &u8| {
+        let (pr, pg, pb) = rgb_from_ansi256(*n);
+        [(r, pr), (g, pg), (b, pb)]
+            .into_iter()
+            .map(|(c, p)| (c as i32 - p as i32).pow(2))
+            .sum::<i32>()
+    };
+    match (0..16).min_by_key(distance).unwrap_or(0) {
+        0 => Color::Black,
+        1 => Color::Red,
+        2 => Color::Green,
+        3 => Color::Yellow,
+        4 => Color::Blue,
+        5 => Color::Magenta,
+        6 => Color::Cyan,
+        7 => Color::White,
+        n => Color::Byte(n),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -60,4 +191,47 @@ mod tests {
     fn color_parsing_returns_correct(#[case] color: Color, #[case] correct: &str) {
         assert_eq!(color.to_string(), correct);
     }
+
+    #[cfg(feature = "std")]
+    #[rstest]
+    #[case("magenta", Ok(Color::Magenta))]
+    #[case("#4269AD", Ok(Color::Full(0x42, 0x69, 0xAD)))]
+    #[case("#D78700", Ok(Color::Full(0xD7, 0x87, 0x00)))]
+    #[case("#D787", Err(AnsiError::InvalidHtml))]
+    #[case("#D7870G", Err(AnsiError::InvalidHtml))]
+    #[case("purple", Err(AnsiError::InvalidHtml))]
+    fn color_from_css(#[case] css: &str, #[case] color: Result<Color, AnsiError>) {
+        assert_eq!(Color::from_css(css), color);
+    }
+
+    #[rstest]
+    #[case(Color::None, 30, "39")]
+    #[case(Color::Cyan, 30, "36")]
+    #[case(Color::Cyan, 40, "46")]
+    #[case(Color::Cyan, 50, "58;5;6")]
+    #[case(Color::Byte(208), 40, "48;5;208")]
+    #[case(Color::Full(1, 2, 3), 50, "58;2;1;2;3")]
+    #[case(Color::Byte(9), 30, "91")]
+    #[case(Color::Byte(15), 40, "107")]
+    #[case(Color::Byte(9), 50, "58;5;9")]
+    fn color_to_sgr(#[case] color: Color, #[case] base: u8, #[case] correct: &str) {
+        assert_eq!(color.to_sgr(base), correct);
+    }
+
+    #[rstest]
+    #[case(
+        Color::Full(255, 135, 0),
+        ColorDepth::TrueColor,
+        Color::Full(255, 135, 0)
+    )]
+    #[case(Color::Full(255, 135, 0), ColorDepth::Palette256, Color::Byte(208))]
+    #[case(Color::Byte(208), ColorDepth::Palette256, Color::Byte(208))]
+    #[case(Color::Full(250, 10, 10), ColorDepth::Palette16, Color::Byte(9))]
+    #[case(Color::Byte(28), ColorDepth::Palette16, Color::Green)]
+    #[case(Color::Byte(12), ColorDepth::Palette16, Color::Byte(12))]
+    #[case(Color::Red, ColorDepth::Palette16, Color::Red)]
+    #[case(Color::Red, ColorDepth::None, Color::None)]
+    fn color_quantization(#[case] color: Color, #[case] depth: ColorDepth, #[case] correct: Color) {
+        assert_eq!(color.quantize(depth), correct);
+    }
 }
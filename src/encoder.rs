@@ -0,0 +1,78 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::{color::ColorDepth, state::AnsiState, AnsiChain};
+
+pub(crate) struct Encoder {}
+
+impl Encoder {
+    pub(crate) fn encode_chain(chain: &AnsiChain, depth: ColorDepth) -> String {
+        let mut ansi = String::new();
+        let mut previous = AnsiState::default();
+        for (state, text) in chain {
+            let state = state.quantized(depth);
+            if state != previous {
+                ansi.push_str(&Encoder::transition(&previous, &state));
+                previous = state;
+            }
+            ansi.push_str(text);
+        }
+        if previous != AnsiState::default() {
+            ansi.push_str(&Encoder::transition(&previous, &AnsiState::default()));
+        }
+        ansi
+    }
+
+    // the shortest escape code going from one state to the next, resetting only if that is shorter
+    fn transition(from: &AnsiState, to: &AnsiState) -> String {
+        let incremental = from.transition_sgr(to).join(";");
+        let reset = ["0".to_string()]
+            .into_iter()
+            .chain(to.to_sgr())
+            .collect::<Vec<_>>()
+            .join(";");
+        if reset.len() < incremental.len() {
+            format!("\x1b[{}m", reset)
+        } else {
+            format!("\x1b[{}m", incremental)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn encode_minimal_transitions() {
+        let chain = vec![
+            (AnsiState::default(), "plain ".to_string()),
+            (AnsiState::from_code("[1m"), "bold ".to_string()),
+            (AnsiState::from_code("[1;31m"), "red".to_string()),
+            (AnsiState::from_code("[3;4;9;32m"), "busy".to_string()),
+            (AnsiState::from_code("[3m"), "italic".to_string()),
+            (AnsiState::default(), " plain".to_string()),
+        ];
+        assert_eq!(
+            Encoder::encode_chain(&chain, ColorDepth::TrueColor),
+            "plain \x1b[1mbold \x1b[31mred\x1b[0;3;4;9;32mbusy\x1b[0;3mitalic\x1b[0m plain"
+        );
+    }
+
+    #[rstest]
+    #[case(ColorDepth::TrueColor, "\x1b[38;2;255;135;0;58;5;9mx\x1b[0m")]
+    #[case(ColorDepth::Palette256, "\x1b[38;5;208;58;5;9mx\x1b[0m")]
+    #[case(ColorDepth::Palette16, "\x1b[33mx\x1b[0m")]
+    #[case(ColorDepth::None, "x")]
+    fn encode_with_color_depth(#[case] depth: ColorDepth, #[case] correct: &str) {
+        let chain = vec![(
+            AnsiState::from_code("[38;2;255;135;0;58;5;9m"),
+            "x".to_string(),
+        )];
+        assert_eq!(Encoder::encode_chain(&chain, depth), correct);
+    }
+}
@@ -5,4 +5,7 @@ pub enum AnsiError {
     InvalidFormat,
     TooLong,
     IllegalCommand,
+    InvalidHtml,
+    StaleIndex,
+    Encode,
 }
@@ -0,0 +1,215 @@
+// the C interface, `include/anstml.h` is generated from this file with cbindgen
+// and the library is built with `cargo rustc --release --lib --crate-type cdylib --features ffi`
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+};
+
+use crate::{error::AnsiError, HtmlConverter, Parser};
+
+/// The parser options, the same as the setters on the Rust `Parser`.
+#[repr(C)]
+#[derive(Default)]
+pub struct AnstmlOptions {
+    /// Turn `c\bc` and `_\bc` overstriking into bold and underlined text.
+    pub decode_overstrike: bool,
+    /// Recognize the 8-bit C1 controls, like U+009B for CSI.
+    pub c1_controls: bool,
+}
+
+/// Html owned by the library, release it with `anstml_buffer_free`. It is not NUL terminated.
+#[repr(C)]
+pub struct AnstmlBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub enum AnstmlStatus {
+    Ok = 0,
+    NumberParse,
+    InvalidStartBrace,
+    InvalidFormat,
+    TooLong,
+    IllegalCommand,
+    InvalidHtml,
+    StaleIndex,
+    Encode,
+    NullPointer,
+    /// A bug in the library, the converter it happened in can't be used any more.
+    Panic,
+}
+
+impl From<AnsiError> for AnstmlStatus {
+    fn from(error: AnsiError) -> Self {
+        match error {
+            AnsiError::NumberParse => AnstmlStatus::NumberParse,
+            AnsiError::InvalidStartBrace => AnstmlStatus::InvalidStartBrace,
+            AnsiError::InvalidFormat => AnstmlStatus::InvalidFormat,
+            AnsiError::TooLong => AnstmlStatus::TooLong,
+            AnsiError::IllegalCommand => AnstmlStatus::IllegalCommand,
+            AnsiError::InvalidHtml => AnstmlStatus::InvalidHtml,
+            AnsiError::StaleIndex => AnstmlStatus::StaleIndex,
+            AnsiError::Encode => AnstmlStatus::Encode,
+        }
+    }
+}
+
+/// Converts utf-8 input fed to it in pieces to html.
+pub struct AnstmlConverter(HtmlConverter);
+
+// a panic unwinding into C is undefined behavior, so every exported function stops it here
+fn guarded(body: impl FnOnce() -> AnstmlStatus) -> AnstmlStatus {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(AnstmlStatus::Panic)
+}
+
+// SAFETY for the helpers: the exported functions pass on pointers their callers promised are valid
+
+fn parser_of(options: *const AnstmlOptions) -> Parser {
+    let default = AnstmlOptions::default();
+    let options = unsafe { options.as_ref() }.unwrap_or(&default);
+    Parser::default()
+        .decode_overstrike(options.decode_overstrike)
+        .c1_controls(options.c1_controls)
+}
+
+fn bytes_of<'a>(data: *const u8, len: usize) -> Option<&'a [u8]> {
+    match (data.is_null(), len) {
+        (_, 0) => Some(&[]),
+        (true, _) => None,
+        (false, _) => Some(unsafe { slice::from_raw_parts(data, len) }),
+    }
+}
+
+fn write_buffer(result: Result<String, AnsiError>, out: *mut AnstmlBuffer) -> AnstmlStatus {
+    match result {
+        Ok(html) => {
+            let bytes = Box::into_raw(html.into_bytes().into_boxed_slice());
+            unsafe {
+                *out = AnstmlBuffer {
+                    data: bytes as *mut u8,
+                    len: bytes.len(),
+                }
+            };
+            AnstmlStatus::Ok
+        }
+        Err(error) => error.into(),
+    }
+}
+
+/// Creates a converter, `options` may be null for the defaults. Returns null if that fails.
+///
+/// # Safety
+/// `options` must be null or point to valid options.
+#[no_mangle]
+pub unsafe extern "C" fn anstml_converter_new(
+    options: *const AnstmlOptions,
+) -> *mut AnstmlConverter {
+    panic::catch_unwind(|| {
+        Box::into_raw(Box::new(AnstmlConverter(HtmlConverter::new(parser_of(
+            options,
+        )))))
+    })
+    .unwrap_or(ptr::null_mut())
+}
+
+/// Converts the next `len` bytes of input, writing the html for it to `out`.
+///
+/// # Safety
+/// `converter` must come from `anstml_converter_new`, `data` must point to `len` readable bytes
+/// and `out` to writable memory for a buffer.
+#[no_mangle]
+pub unsafe extern "C" fn anstml_converter_feed(
+    converter: *mut AnstmlConverter,
+    data: *const u8,
+    len: usize,
+    out: *mut AnstmlBuffer,
+) -> AnstmlStatus {
+    guarded(|| {
+        let Some(bytes) = bytes_of(data, len).filter(|_| !converter.is_null() && !out.is_null())
+        else {
+            return AnstmlStatus::NullPointer;
+        };
+        write_buffer((*converter).0.feed(bytes), out)
+    })
+}
+
+/// Writes the end of the html to `out` and frees the converter, also when conversion fails.
+///
+/// # Safety
+/// `converter` must come from `anstml_converter_new` and can't be used afterwards, `out` must
+/// point to writable memory for a buffer.
+#[no_mangle]
+pub unsafe extern "C" fn anstml_converter_finish(
+    converter: *mut AnstmlConverter,
+    out: *mut AnstmlBuffer,
+) -> AnstmlStatus {
+    guarded(|| {
+        if converter.is_null() {
+            return AnstmlStatus::NullPointer;
+        }
+        let converter = Box::from_raw(converter);
+        if out.is_null() {
+            return AnstmlStatus::NullPointer;
+        }
+        write_buffer(converter.0.finish(), out)
+    })
+}
+
+/// Frees a converter without finishing it.
+///
+/// # Safety
+/// `converter` must be null or come from `anstml_converter_new`, and can't be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn anstml_converter_free(converter: *mut AnstmlConverter) {
+    guarded(|| {
+        if !converter.is_null() {
+            drop(Box::from_raw(converter));
+        }
+        AnstmlStatus::Ok
+    });
+}
+
+/// Converts `len` bytes of utf-8 input to html at once.
+///
+/// # Safety
+/// `data` must point to `len` readable bytes, `options` must be null or point to valid options
+/// and `out` must point to writable memory for a buffer.
+#[no_mangle]
+pub unsafe extern "C" fn anstml_convert(
+    data: *const u8,
+    len: usize,
+    options: *const AnstmlOptions,
+    out: *mut AnstmlBuffer,
+) -> AnstmlStatus {
+    guarded(|| {
+        let Some(bytes) = bytes_of(data, len).filter(|_| !out.is_null()) else {
+            return AnstmlStatus::NullPointer;
+        };
+        let mut converter = HtmlConverter::new(parser_of(options));
+        let html = converter.feed(bytes).and_then(|mut html| {
+            html.push_str(&converter.finish()?);
+            Ok(html)
+        });
+        write_buffer(html, out)
+    })
+}
+
+/// Frees a buffer returned by the library.
+///
+/// # Safety
+/// `buffer` must come from this library and can't be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn anstml_buffer_free(buffer: AnstmlBuffer) {
+    guarded(|| {
+        if !buffer.data.is_null() {
+            drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+                buffer.data,
+                buffer.len,
+            )));
+        }
+        AnstmlStatus::Ok
+    });
+}
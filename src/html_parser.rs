@@ -0,0 +1,85 @@
+use crate::{error::AnsiError, state::AnsiState, AnsiChain};
+
+// reads the `<pre>` produced by `Formatter::format_chain` back into a chain
+pub(crate) fn parse_preformatted(html: &str) -> Result<AnsiChain, AnsiError> {
+    let (_, mut rest) = html
+        .trim()
+        .strip_prefix("<pre")
+        .and_then(|html| html.strip_suffix("</pre>"))
+        .and_then(|html| html.split_once('>'))
+        .ok_or(AnsiError::InvalidHtml)?;
+
+    let mut chain: AnsiChain = Vec::new();
+    while !rest.is_empty() {
+        let (state, text) = if let Some(span) = rest.strip_prefix("<span") {
+            let (attributes, span) = span.split_once('>').ok_or(AnsiError::InvalidHtml)?;
+            let (text, after) = span.split_once("</span>").ok_or(AnsiError::InvalidHtml)?;
+            rest = after;
+            (parse_attributes(attributes)?, text)
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            if end == 0 {
+                return Err(AnsiError::InvalidHtml);
+            }
+            let text = &rest[..end];
+            rest = &rest[end..];
+            (AnsiState::default(), text)
+        };
+        // anstml never nests tags
+        if text.contains('<') {
+            return Err(AnsiError::InvalidHtml);
+        }
+        let text = html_escape::decode_html_entities(text);
+        match chain.last_mut() {
+            Some((last_state, last_text)) if *last_state == state => last_text.push_str(&text),
+            _ => chain.push((state, text.into_owned())),
+        }
+    }
+    Ok(chain)
+}
+
+// only the style attribute carries the state, a span styled by classes alone can't be read
+fn parse_attributes(attributes: &str) -> Result<AnsiState, AnsiError> {
+    match attributes.split_once(" style=\"") {
+        Some((_, style)) => {
+            let (style, _) = style.split_once('"').ok_or(AnsiError::InvalidHtml)?;
+            AnsiState::from_style(&html_escape::decode_html_entities(style))
+        }
+        None if attributes.contains(" class=") => Err(AnsiError::InvalidHtml),
+        None => Ok(AnsiState::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn parse_formatted_html() {
+        let html = "<pre>default &lt;text&gt;\n<span style=\"font-weight:bold;\">bold</span>\
+            <span class=\"x\" style=\"font-weight:bold;\"> still bold</span><span>plain</span></pre>";
+        let mut bold = AnsiState::default();
+        bold.parse_ansi_code(&mut "[1m".chars()).unwrap();
+        assert_eq!(
+            parse_preformatted(html),
+            Ok(vec![
+                (AnsiState::default(), "default <text>\n".to_string()),
+                (bold, "bold still bold".to_string()),
+                (AnsiState::default(), "plain".to_string()),
+            ])
+        );
+    }
+
+    #[rstest]
+    #[case("<div>text</div>")]
+    #[case("<pre>text")]
+    #[case("<pre><span style=\"font-weight:bold;\">text</pre>")]
+    #[case("<pre><span style=\"font-weight:bold;>text</span></pre>")]
+    #[case("<pre><span><b>text</b></span></pre>")]
+    #[case("<pre><b>text</b></pre>")]
+    #[case("<pre><span class=\"bold\">text</span></pre>")]
+    fn invalid_html(#[case] html: &str) {
+        assert_eq!(parse_preformatted(html), Err(AnsiError::InvalidHtml));
+    }
+}
@@ -0,0 +1,481 @@
+use std::{fmt, ops::Range, str::FromStr};
+
+use itertools::Itertools;
+
+use crate::{
+    error::AnsiError, state::AnsiState, sub_parsers::parse_charset_designation, Formatter, Parser,
+};
+
+const INDEX_VERSION: &str = "anstml-index 3";
+
+pub(crate) enum LineStart {
+    Skip,
+    Keep,
+    // keep this one and stop scanning
+    Last,
+}
+
+impl Parser {
+    // runs only the escape codes of `text`, taking a snapshot of the parser at the line starts `visit` keeps.
+    // line starts inside an escape code or right before a backspace are never visited, parsing can't resume there
+    pub(crate) fn line_starts(
+        &self,
+        text: &str,
+        mut visit: impl FnMut(usize, usize) -> LineStart,
+    ) -> Result<Vec<(usize, usize, Parser)>, AnsiError> {
+        let bytes = text.as_bytes();
+        let mut scanner = self.snapshot();
+        let mut snapshots = vec![(0, 0, self.snapshot())];
+        let mut position = 0;
+        let mut line = 0;
+        while position < text.len() {
+            let end = scanner
+                .find_escape(&bytes[position..])
+                .map_or(text.len(), |found| position + found);
+            for found in memchr::memchr_iter(b'\n', &bytes[position..end]) {
+                let start = position + found + 1;
+                line += 1;
+                if start == text.len() || bytes[start] == b'\x08' {
+                    continue;
+                }
+                let last = match visit(start, line) {
+                    LineStart::Skip => continue,
+                    LineStart::Keep => false,
+                    LineStart::Last => true,
+                };
+                let mut snapshot = scanner.snapshot();
+                snapshot.track_shifts(&bytes[position..start]);
+                snapshots.push((start, line, snapshot));
+                if last {
+                    return Ok(snapshots);
+                }
+            }
+            scanner.track_shifts(&bytes[position..end]);
+            if end == text.len() {
+                break;
+            }
+
+            let (result, consumed) = scanner.parse_escape_str(&text[end..]);
+            position = end + consumed;
+            line += memchr::memchr_iter(b'\n', &bytes[end..position]).count();
+            if let Err(ansi_error) = result {
+                if position < text.len() {
+                    return Err(ansi_error);
+                }
+            }
+        }
+        Ok(snapshots)
+    }
+
+    pub(crate) fn snapshot(&self) -> Parser {
+        Parser {
+            ansi_chain: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    // the shift state the last shift in or shift out control of some text leaves behind
+    fn track_shifts(&mut self, bytes: &[u8]) {
+        if let Some(index) = memchr::memrchr2(b'\x0e', b'\x0f', bytes) {
+            self.shifted = bytes[index] == b'\x0e';
+        }
+    }
+}
+
+// parser snapshots every so many lines of a text, to render parts of it without parsing everything before them
+#[derive(Debug, PartialEq, Clone)]
+pub struct LineIndex {
+    every: usize,
+    // the indexed text, which may only have grown since
+    length: usize,
+    checkpoints: Vec<(usize, usize, Parser)>,
+    // a hash of the text from each checkpoint up to the next one, so a range is checked
+    // without reading everything before it
+    hashes: Vec<u64>,
+}
+
+impl LineIndex {
+    pub fn build(parser: &Parser, text: &str, every: usize) -> Result<LineIndex, AnsiError> {
+        let every = every.max(1);
+        let mut next = every;
+        let checkpoints = parser.line_starts(text, |_, line| {
+            if line < next {
+                return LineStart::Skip;
+            }
+            next = line + every;
+            LineStart::Keep
+        })?;
+        let hashes = checkpoints
+            .iter()
+            .map(|(offset, _, _)| *offset)
+            .chain([text.len()])
+            .tuple_windows()
+            .map(|(start, end)| fingerprint(&text.as_bytes()[start..end]))
+            .collect();
+        Ok(LineIndex {
+            every,
+            length: text.len(),
+            checkpoints,
+            hashes,
+        })
+    }
+
+    // the byte offset, line number and style at each checkpoint
+    pub fn checkpoints(&self) -> impl Iterator<Item = (usize, usize, &AnsiState)> {
+        self.checkpoints
+            .iter()
+            .map(|(offset, line, parser)| (*offset, *line, &parser.current))
+    }
+
+    // the part of the text `render_slice` needs for `lines`: from the checkpoint before them up to
+    // the one after them, or to the end of the text if the lines go past the last checkpoint
+    pub fn byte_range(&self, lines: Range<usize>) -> (usize, Option<usize>) {
+        let (first, last) = self.blocks(&lines);
+        let end = self.checkpoints.get(last).map(|(offset, _, _)| *offset);
+        (self.checkpoints[first].0, end)
+    }
+
+    // renders the lines in `lines` (counting from 0) of the text the index was built from,
+    // or of that text with more appended to it
+    pub fn render_range(&self, text: &str, lines: Range<usize>) -> Result<String, AnsiError> {
+        let (start, end) = self.byte_range(lines.clone());
+        let slice = text
+            .get(start..end.unwrap_or(text.len()))
+            .ok_or(AnsiError::StaleIndex)?;
+        self.render_slice(slice, lines)
+    }
+
+    // `render_range` for only the part of the text `byte_range` gives, so a huge file doesn't
+    // have to be read into memory to render a few lines of it
+    pub fn render_slice(&self, slice: &str, lines: Range<usize>) -> Result<String, AnsiError> {
+        let mut html = String::from("<pre>");
+        if !lines.is_empty() {
+            let (nearest, after) = self.blocks(&lines);
+            let (offset, line, parser) = &self.checkpoints[nearest];
+            // only the blocks the lines are in are checked, text appended after the index is not
+            for block in nearest..after {
+                let start = self.checkpoints[block].0 - offset;
+                let end = self
+                    .checkpoints
+                    .get(block + 1)
+                    .map_or(self.length, |(next, _, _)| *next)
+                    - offset;
+                if slice.as_bytes().get(start..end).map(fingerprint) != Some(self.hashes[block]) {
+                    return Err(AnsiError::StaleIndex);
+                }
+            }
+            let (first, end) = (lines.start - line, lines.end - line);
+            let mut started = first == 0;
+            let snapshots = parser.line_starts(slice, |_, line| {
+                if line >= end {
+                    LineStart::Last
+                } else if line >= first && !started {
+                    started = true;
+                    LineStart::Keep
+                } else {
+                    LineStart::Skip
+                }
+            })?;
+            let start = snapshots.iter().find(|(_, line, _)| *line >= first);
+            let stop = snapshots
+                .iter()
+                .find(|(_, line, _)| *line >= end)
+                .map_or(slice.len(), |(offset, _, _)| *offset);
+            if let Some((start, _, parser)) = start.filter(|(start, _, _)| *start < stop) {
+                for (state, text) in parser.clone().parse_str(&slice[*start..stop])? {
+                    Formatter::write_run(&state, &text, &mut html)
+                        .expect("writing to a String can't fail");
+                }
+            }
+        }
+        html.push_str("</pre>");
+        Ok(html)
+    }
+
+    // the checkpoint the lines are rendered from, and the first one after them
+    fn blocks(&self, lines: &Range<usize>) -> (usize, usize) {
+        let after = |line| self.checkpoints.partition_point(|(_, at, _)| *at <= line);
+        let first = after(lines.start) - 1;
+        (
+            first,
+            after(lines.end.max(lines.start + 1) - 1).max(first + 1),
+        )
+    }
+}
+
+// one line per checkpoint: offset, line, the hash up to the next checkpoint, style, the G0 and G1
+// charsets, the shift state and the pushed styles
+impl fmt::Display for LineIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flag = |on: bool| if on { 1 } else { 0 };
+        let (_, _, parser) = &self.checkpoints[0];
+        writeln!(
+            f,
+            "{} {} {} {} {}",
+            INDEX_VERSION,
+            self.every,
+            self.length,
+            flag(parser.decode_overstrike),
+            flag(parser.c1_controls)
+        )?;
+        for ((offset, line, parser), hash) in self.checkpoints.iter().zip(&self.hashes) {
+            write!(
+                f,
+                "{} {} {:016x} {} {}{} {}",
+                offset,
+                line,
+                hash,
+                parser.current,
+                parser.charsets[0].designator(),
+                parser.charsets[1].designator(),
+                flag(parser.shifted)
+            )?;
+            for (state, attributes) in &parser.sgr_stack {
+                write!(f, " {}/{}", state, attributes.iter().join(","))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for LineIndex {
+    type Err = AnsiError;
+
+    fn from_str(index: &str) -> Result<Self, Self::Err> {
+        let mut lines = index.lines();
+        let header = lines
+            .next()
+            .and_then(|header| header.strip_prefix(INDEX_VERSION))
+            .ok_or(AnsiError::InvalidFormat)?;
+        let [every, length, overstrike, c1_controls] =
+            header.split_whitespace().collect::<Vec<_>>()[..]
+        else {
+            return Err(AnsiError::InvalidFormat);
+        };
+        let number = |field: &str| field.parse().map_err(|_| AnsiError::NumberParse);
+        let (every, length, overstrike, c1_controls): (usize, usize, usize, usize) = (
+            number(every)?,
+            number(length)?,
+            number(overstrike)?,
+            number(c1_controls)?,
+        );
+        let options = Parser::default()
+            .decode_overstrike(overstrike == 1)
+            .c1_controls(c1_controls == 1);
+
+        let mut checkpoints = Vec::new();
+        let mut hashes = Vec::new();
+        for line in lines {
+            let mut fields = line.split(' ');
+            let mut field = || fields.next().ok_or(AnsiError::InvalidFormat);
+            let (offset, line) = (number(field()?)?, number(field()?)?);
+            hashes.push(u64::from_str_radix(field()?, 16).map_err(|_| AnsiError::NumberParse)?);
+            let mut parser = options.clone();
+            parser.current = state_of(field()?)?;
+            let designators = field()?;
+            if designators.len() != 2 {
+                return Err(AnsiError::InvalidFormat);
+            }
+            for (slot, designator) in designators.chars().enumerate() {
+                let designation = format!("({}", designator);
+                (_, parser.charsets[slot]) = parse_charset_designation(&mut designation.chars())
+                    .ok_or(AnsiError::InvalidFormat)?;
+            }
+            parser.shifted = field()? == "1";
+            for pushed in fields {
+                let (state, attributes) = pushed.split_once('/').ok_or(AnsiError::InvalidFormat)?;
+                let attributes = attributes
+                    .split(',')
+                    .filter(|attribute| !attribute.is_empty())
+                    .map(|attribute| attribute.parse().map_err(|_| AnsiError::NumberParse))
+                    .try_collect()?;
+                parser.sgr_stack.push((state_of(state)?, attributes));
+            }
+            checkpoints.push((offset, line, parser));
+        }
+        if checkpoints
+            .first()
+            .map(|(offset, line, _)| (*offset, *line))
+            != Some((0, 0))
+            || checkpoints.iter().tuple_windows().any(
+                |((offset, line, _), (next_offset, next_line, _))| {
+                    offset >= next_offset || line >= next_line
+                },
+            )
+            || checkpoints.iter().any(|(offset, _, _)| *offset > length)
+        {
+            return Err(AnsiError::InvalidFormat);
+        }
+        Ok(LineIndex {
+            every,
+            length,
+            checkpoints,
+            hashes,
+        })
+    }
+}
+
+// FNV-1a, which unlike `DefaultHasher` stays the same between builds so it can be stored
+fn fingerprint(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn state_of(sgr: &str) -> Result<AnsiState, AnsiError> {
+    let state: AnsiState = sgr.parse()?;
+    // only what `Display` writes is accepted, codes that don't survive a round trip aren't
+    if state.to_string() != sgr {
+        return Err(AnsiError::InvalidFormat);
+    }
+    Ok(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{log, merged};
+    use rstest::rstest;
+
+    // the lines cut out of the whole text, with the state each one starts in
+    fn sequential_range(text: &str, lines: Range<usize>) -> String {
+        let mut parser = Parser::default();
+        let mut html = String::from("<pre>");
+        let mut line = 0;
+        for (state, part) in parser.parse_str(text).unwrap() {
+            for piece in part.split_inclusive('\n') {
+                if lines.contains(&line) {
+                    Formatter::write_run(&state, piece, &mut html).unwrap();
+                }
+                line += piece.ends_with('\n') as usize;
+            }
+        }
+        html + "</pre>"
+    }
+
+    #[rstest]
+    #[case(0..1)]
+    #[case(0..100)]
+    #[case(7..8)]
+    #[case(20..31)]
+    #[case(99..150)]
+    #[case(150..160)]
+    #[case(40..40)]
+    fn render_ranges_like_the_whole_text(#[case] lines: Range<usize>) {
+        let log = log(200);
+        let index = LineIndex::build(&Parser::default(), &log, 10).unwrap();
+        assert_eq!(
+            index.render_range(&log, lines.clone()).map(merged),
+            Ok(merged(sequential_range(&log, lines)))
+        );
+    }
+
+    #[test]
+    fn index_skips_lines_inside_escapes() {
+        let log = format!(
+            "{}\x1b]0;a\nb\x07\n{}",
+            "a\n".repeat(9),
+            "\x1b[1mb\n".repeat(20)
+        );
+        let index = LineIndex::build(&Parser::default(), &log, 10).unwrap();
+        let lines: Vec<_> = index.checkpoints().map(|(_, line, _)| line).collect();
+        // line 10 starts inside the OSC string, so its checkpoint moves on to line 11
+        assert_eq!(lines, vec![0, 11, 21]);
+        assert_eq!(
+            index.render_range(&log, 10..11),
+            Ok("<pre></pre>".to_string())
+        );
+        assert_eq!(
+            index.render_range(&log, 10..12),
+            Ok("<pre><span style=\"font-weight:bold;\">b\n</span></pre>".to_string())
+        );
+        assert_eq!(
+            index.render_range(&log[1..], 0..1),
+            Err(AnsiError::StaleIndex)
+        );
+    }
+
+    #[test]
+    fn render_appended_text() {
+        let log = log(200);
+        let index = LineIndex::build(&Parser::default(), &log, 10).unwrap();
+        let grown = format!("{}\x1b[1mappended\nlines\n", log);
+        assert_eq!(
+            index.render_range(&grown, 95..102).map(merged),
+            Ok(merged(sequential_range(&grown, 95..102)))
+        );
+        let edited = log.replacen("line 0", "line X", 1);
+        assert_eq!(
+            index.render_range(&edited, 0..1),
+            Err(AnsiError::StaleIndex)
+        );
+    }
+
+    #[rstest]
+    #[case(7..8)]
+    #[case(20..31)]
+    #[case(95..150)]
+    fn render_from_a_slice(#[case] lines: Range<usize>) {
+        let log = log(200);
+        let index = LineIndex::build(&Parser::default(), &log, 10).unwrap();
+        let (start, end) = index.byte_range(lines.clone());
+        let slice = &log[start..end.unwrap_or(log.len())];
+        assert!(slice.len() < log.len() / 2);
+        assert_eq!(
+            index.render_slice(slice, lines.clone()),
+            index.render_range(&log, lines)
+        );
+    }
+
+    #[test]
+    fn check_only_the_rendered_blocks() {
+        let log = log(200);
+        let index = LineIndex::build(&Parser::default(), &log, 10).unwrap();
+        let edited = log.replacen("line 0", "line X", 1);
+        assert_eq!(
+            index.render_range(&edited, 50..60),
+            index.render_range(&log, 50..60)
+        );
+        assert_eq!(
+            index.render_range(&edited, 5..15),
+            Err(AnsiError::StaleIndex)
+        );
+        let (start, _) = index.byte_range(50..60);
+        assert_eq!(
+            index.render_slice(&log[start..start + 10], 50..60),
+            Err(AnsiError::StaleIndex)
+        );
+    }
+
+    #[test]
+    fn serialize_index() {
+        let log = log(200);
+        let parser = Parser::default().decode_overstrike(true);
+        let index = LineIndex::build(&parser, &log, 2).unwrap();
+        let text = index.to_string();
+        assert!(text.starts_with("anstml-index 3 2 "));
+        assert!(text.contains("\n104 4 931384ea30b90576 0;1;4;30 0B 1 0;30/\n"));
+        assert!(text.contains("\n229 11 f84dabb5fc4e63e6 0;37 BB 0\n"));
+        assert_eq!(text.parse(), Ok(index));
+    }
+
+    #[rstest]
+    #[case("anstml-index 2 3 10 0 0\n0 0 0 0 BB 0", AnsiError::InvalidFormat)]
+    #[case("anstml-index 3 3 10 0 0\n0 0 0 0;1;x B 0", AnsiError::InvalidFormat)]
+    #[case("anstml-index 3 3 10 0 0\n4 1 0 0 BB 0", AnsiError::InvalidFormat)]
+    #[case("anstml-index 3 3 10 0 0\n0 0 0 8 BB 0", AnsiError::InvalidFormat)]
+    #[case("anstml-index 3 3 10 0 0\n0 0 0 0;8 BB 0", AnsiError::InvalidFormat)]
+    #[case(
+        "anstml-index 3 3 10 0 0\n0 0 0 0;1 BB 0 0;53/",
+        AnsiError::InvalidFormat
+    )]
+    #[case(
+        "anstml-index 3 3 10 0 0\n0 0 0 0 BB 0\n0 0 0 0 BB 0",
+        AnsiError::InvalidFormat
+    )]
+    #[case("anstml-index 3 3 10 0 0\n0 0 xyz 0 BB 0", AnsiError::NumberParse)]
+    fn invalid_indexes(#[case] index: &str, #[case] error: AnsiError) {
+        assert_eq!(index.parse::<LineIndex>(), Err(error));
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{
+    color::Color,
+    state::{AnsiState, Intensity, Italics, Underline},
+    AnsiChain,
+};
+
+#[derive(Default)]
+pub struct LatexRenderer {
+    standalone: bool,
+}
+
+impl LatexRenderer {
+    // wrap the output in a complete document instead of only the `Verbatim` environment
+    pub fn standalone(mut self, standalone: bool) -> Self {
+        self.standalone = standalone;
+        self
+    }
+
+    // the packages and color definitions the output of `render` needs
+    pub fn preamble(&self, chain: &AnsiChain) -> String {
+        let mut preamble = "\\usepackage{xcolor}\n\\usepackage{fancyvrb}\n\
+            \\newcommand{\\ansibg}[2]{{\\setlength{\\fboxsep}{0pt}\\colorbox{#1}{\\strut #2}}}\n"
+            .to_string();
+        let mut defined = Vec::new();
+        for (state, _) in chain {
+            for color in [&state.text_color, &state.background_color] {
+                if let Some(hex) = color.to_hex() {
+                    let hex = hex[1..].to_string();
+                    if !defined.contains(&hex) {
+                        preamble
+                            .push_str(&format!("\\definecolor{{ansi{0}}}{{HTML}}{{{0}}}\n", hex));
+                        defined.push(hex);
+                    }
+                }
+            }
+        }
+        preamble
+    }
+
+    pub fn render(&self, chain: &AnsiChain) -> String {
+        let mut latex = String::from("\\begin{Verbatim}[commandchars=\\\\\\{\\}]\n");
+        for (state, text) in chain {
+            // a colorbox can't span lines, so every line is wrapped on its own
+            for (index, line) in text.split('\n').enumerate() {
+                if index > 0 {
+                    latex.push('\n');
+                }
+                if !line.is_empty() {
+                    latex.push_str(&wrap(state, &escape(line)));
+                }
+            }
+        }
+        if !latex.ends_with('\n') {
+            latex.push('\n');
+        }
+        latex.push_str("\\end{Verbatim}\n");
+        if self.standalone {
+            format!(
+                "\\documentclass{{article}}\n{}\\begin{{document}}\n{}\\end{{document}}\n",
+                self.preamble(chain),
+                latex
+            )
+        } else {
+            latex
+        }
+    }
+}
+
+fn color_name(color: &Color) -> Option<String> {
+    color.to_hex().map(|hex| format!("ansi{}", &hex[1..]))
+}
+
+// strikethrough, blinking and faint text have no counterpart without extra packages
+fn wrap(state: &AnsiState, text: &str) -> String {
+    let mut text = text.to_string();
+    if state.underline != Underline::None {
+        text = format!("\\underline{{{}}}", text);
+    }
+    if state.italic == Italics::Yes {
+        text = format!("\\textit{{{}}}", text);
+    }
+    if state.intensity == Intensity::Bold {
+        text = format!("\\textbf{{{}}}", text);
+    }
+    let (foreground, background) =
+        state.effective_colors(color_name, "black".to_string(), "white".to_string());
+    if let Some(foreground) = foreground {
+        text = format!("\\textcolor{{{}}}{{{}}}", foreground, text);
+    }
+    if let Some(background) = background {
+        text = format!("\\ansibg{{{}}}{{{}}}", background, text);
+    }
+    text
+}
+
+// with `commandchars=\\\{\}` only the backslash and braces are special
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\char92{}"),
+            '{' => escaped.push_str("\\char123{}"),
+            '}' => escaped.push_str("\\char125{}"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_special_characters() {
+        assert_eq!(
+            escape("\\{x}$ 100% #1 & ~_^"),
+            "\\char92{}\\char123{}x\\char125{}$ 100% #1 & ~_^"
+        );
+    }
+
+    #[test]
+    fn render_chain_as_verbatim() {
+        let chain = vec![
+            (AnsiState::default(), "$ ls\n".to_string()),
+            (AnsiState::from_code("[1;3;4;31;44m"), "a{b}\nc".to_string()),
+            (AnsiState::from_code("[7m"), " inverted".to_string()),
+        ];
+        let renderer = LatexRenderer::default();
+        assert_eq!(
+            renderer.render(&chain),
+            "\\begin{Verbatim}[commandchars=\\\\\\{\\}]\n\
+            $ ls\n\
+            \\ansibg{ansi0000EE}{\\textcolor{ansiCD0000}{\\textbf{\\textit{\\underline{a\\char123{}b\\char125{}}}}}}\n\
+            \\ansibg{ansi0000EE}{\\textcolor{ansiCD0000}{\\textbf{\\textit{\\underline{c}}}}}\
+            \\ansibg{black}{\\textcolor{white}{ inverted}}\n\
+            \\end{Verbatim}\n"
+        );
+        assert_eq!(
+            renderer.preamble(&chain),
+            "\\usepackage{xcolor}\n\\usepackage{fancyvrb}\n\
+            \\newcommand{\\ansibg}[2]{{\\setlength{\\fboxsep}{0pt}\\colorbox{#1}{\\strut #2}}}\n\
+            \\definecolor{ansiCD0000}{HTML}{CD0000}\n\
+            \\definecolor{ansi0000EE}{HTML}{0000EE}\n"
+        );
+    }
+
+    #[test]
+    fn standalone_document() {
+        let chain = vec![(AnsiState::default(), "text".to_string())];
+        let latex = LatexRenderer::default().standalone(true).render(&chain);
+        assert!(latex.starts_with("\\documentclass{article}\n\\usepackage{xcolor}\n"));
+        assert!(latex.ends_with("text\n\\end{Verbatim}\n\\end{document}\n"));
+    }
+}
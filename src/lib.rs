@@ -1,51 +1,422 @@
 // needed for the html crate
-#![recursion_limit = "512"]
+#![cfg_attr(feature = "html", recursion_limit = "512")]
+// the parser and the style model only need `alloc`, the output formats need `std`
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
+extern crate alloc;
+
+use alloc::{
+    borrow::Cow,
+    string::{String, ToString},
+    vec::Vec,
+};
+#[cfg(feature = "tokio")]
+pub use async_io::{convert_async, html_stream};
+use charset::Charset;
+#[cfg(feature = "std")]
+pub use chat::{BbcodeRenderer, DiscordRenderer, IrcRenderer};
+pub use color::{Color, ColorDepth};
+use core::ops::Range;
+use encoder::Encoder;
 use error::AnsiError;
+#[cfg(feature = "html")]
 use html::{inline_text::Span, text_content::PreformattedText};
+#[cfg(feature = "std")]
+pub use index::LineIndex;
 use itertools::Itertools;
-use state::AnsiState;
+#[cfg(feature = "std")]
+pub use latex::LatexRenderer;
+#[cfg(feature = "std")]
+pub use parallel::convert_parallel;
+#[cfg(feature = "png")]
+pub use raster::PngRenderer;
+#[cfg(feature = "std")]
+pub use richtext::{PangoRenderer, QtRenderer};
+#[cfg(feature = "std")]
+pub use rtf::RtfRenderer;
+#[cfg(feature = "serde")]
+pub use schema::StyledRun;
+#[cfg(feature = "std")]
+pub use semantic::{SemanticProfile, SemanticRenderer};
+use state::SgrStackOperation;
+pub use state::{
+    AnsiState, Blink, Intensity, InvertColors, Italics, Script, Spacing, StrikeThrough, Underline,
+};
+#[cfg(feature = "std")]
+use std::{fmt, io};
+#[cfg(feature = "std")]
+pub use stream::HtmlConverter;
+use sub_parsers::{
+    parse_charset_designation, parse_overstrike, parse_sgr_stack_operation, skip_control_string,
+    C1Controls,
+};
+#[cfg(feature = "std")]
+pub use svg::SvgRenderer;
 
+#[cfg(feature = "tokio")]
+mod async_io;
+mod charset;
+#[cfg(feature = "std")]
+mod chat;
 mod color;
+mod encoder;
 pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+mod html_parser;
+#[cfg(feature = "std")]
+mod index;
+#[cfg(feature = "std")]
+mod latex;
+#[cfg(feature = "std")]
+mod parallel;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "png")]
+mod raster;
+#[cfg(feature = "std")]
+mod richtext;
+#[cfg(feature = "std")]
+mod rtf;
+#[cfg(feature = "serde")]
+mod schema;
+#[cfg(feature = "std")]
+mod semantic;
 mod state;
+#[cfg(feature = "std")]
+mod stream;
 mod sub_parsers;
+#[cfg(feature = "std")]
+mod svg;
+#[cfg(all(test, feature = "std"))]
+mod test_support;
+
+// xterm keeps at most 10 pushed renditions, further pushes are ignored
+const SGR_STACK_LIMIT: usize = 10;
+// an unfinished escape code longer than this is given up on and its text shown, so an
+// unterminated control string can't hold back all further input
+const PENDING_LIMIT: usize = 4096;
 
-#[derive(Default)]
+#[derive(Debug, PartialEq, Default, Clone)]
 pub struct Parser {
     ansi_chain: AnsiChain,
     current: AnsiState,
+    sgr_stack: Vec<(AnsiState, Vec<u8>)>,
+    decode_overstrike: bool,
+    c1_controls: bool,
+    // the G0 and G1 character sets, and whether G1 is shifted in
+    charsets: [Charset; 2],
+    shifted: bool,
+    // an escape code cut off at the end of the last input, finished by the next one
+    pending: String,
 }
 
 pub type AnsiChain = Vec<(AnsiState, String)>;
+// a chain whose text borrows from the parsed input where it could be left untouched
+pub type BorrowedChain<'a> = Vec<(AnsiState, Cow<'a, str>)>;
+// the byte ranges of the plain text each style applies to
+pub type StyledRanges = Vec<(Range<usize>, AnsiState)>;
 
 impl Parser {
+    // starts parsing in the given style, like the one an earlier parser ended in, or one stored
+    // with `to_string` and read back with `parse`
+    pub fn with_state(state: AnsiState) -> Parser {
+        Parser {
+            current: state,
+            ..Default::default()
+        }
+    }
+
+    // turn `c\bc` and `_\bc` from man pages and the like into bold and underlined text
+    pub fn decode_overstrike(mut self, decode_overstrike: bool) -> Self {
+        self.decode_overstrike = decode_overstrike;
+        self
+    }
+
+    // recognize the 8-bit C1 controls (like 0x9B for CSI) as their 7-bit escape sequences
+    pub fn c1_controls(mut self, c1_controls: bool) -> Self {
+        self.c1_controls = c1_controls;
+        self
+    }
+
+    pub fn ansi_chain(&self) -> &AnsiChain {
+        &self.ansi_chain
+    }
+
+    // the chain parsed since the last call, every parse appends to it
+    pub fn take_chain(&mut self) -> AnsiChain {
+        core::mem::take(&mut self.ansi_chain)
+    }
+
+    // the style the parsed text ends in
+    pub fn current_state(&self) -> &AnsiState {
+        &self.current
+    }
+
+    pub fn plain_text(&self) -> String {
+        self.ansi_chain
+            .iter()
+            .map(|(_, text)| text.as_str())
+            .collect()
+    }
+
+    pub fn attributed_text(&self) -> (String, StyledRanges) {
+        let mut text = String::new();
+        let mut ranges = Vec::new();
+        for (state, part) in &self.ansi_chain {
+            let start = text.len();
+            text.push_str(part);
+            ranges.push((start..text.len(), state.clone()));
+        }
+        (text, ranges)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn into_html(self) -> String {
+        let mut html = String::new();
+        self.write_html(&mut html)
+            .expect("writing to a String can't fail");
+        html
+    }
+
+    #[cfg(feature = "std")]
+    pub fn write_html<W: fmt::Write>(&self, out: &mut W) -> fmt::Result {
+        Formatter::write_chain(&self.ansi_chain, out)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn write_html_io<W: io::Write>(&self, out: &mut W) -> io::Result<()> {
+        let mut adapter = IoAdapter {
+            inner: out,
+            error: Ok(()),
+        };
+        match self.write_html(&mut adapter) {
+            Ok(()) => Ok(()),
+            Err(fmt::Error) => adapter.error,
+        }
+    }
+
+    #[cfg(feature = "html")]
+    pub fn into_preformatted(self) -> PreformattedText {
+        Formatter::format_chain(self.ansi_chain)
+    }
+
     pub fn parse_ansi_text<T: Iterator<Item = char> + Clone>(
         &mut self,
         characters: &mut T,
+    ) -> Result<(), AnsiError> {
+        if !self.pending.is_empty() {
+            let mut joined = core::mem::take(&mut self.pending);
+            joined.extend(characters);
+            return self.parse_ansi_text(&mut joined.chars());
+        }
+        if self.c1_controls {
+            let mut translated = C1Controls::new(characters.clone());
+            let result = self.parse_chain(&mut translated);
+            *characters = translated.into_inner();
+            result
+        } else {
+            self.parse_chain(characters)
+        }
+    }
+
+    // parses a whole string at once, jumping between escape codes instead of walking every char
+    // unlike `parse_ansi_text` this returns the runs of this call only, leaving `ansi_chain` alone
+    pub fn parse_str<'a>(&mut self, text: &'a str) -> Result<BorrowedChain<'a>, AnsiError> {
+        if !self.pending.is_empty() {
+            let joined = core::mem::take(&mut self.pending) + text;
+            return Ok(self
+                .parse_str(&joined)?
+                .into_iter()
+                .map(|(state, text)| (state, Cow::Owned(text.into_owned())))
+                .collect());
+        }
+        let mut chain: BorrowedChain = Vec::new();
+        let mut position = 0;
+        while position < text.len() {
+            let end = self
+                .find_escape(&text.as_bytes()[position..])
+                .map_or(text.len(), |found| position + found);
+            let part = &text[position..end];
+            if self.decode_overstrike && part.contains('\x08') {
+                let part = self.translate_charsets(part.to_string());
+                chain.extend(
+                    parse_overstrike(&part, &self.current)
+                        .into_iter()
+                        .map(|(state, text)| (state, Cow::Owned(text))),
+                );
+            } else if !part.is_empty() {
+                let part =
+                    if part.contains(['\x0e', '\x0f']) || self.charsets != [Charset::Ascii; 2] {
+                        Cow::Owned(self.translate_charsets(part.to_string()))
+                    } else {
+                        Cow::Borrowed(part)
+                    };
+                chain.push((self.current.clone(), part));
+            }
+            if end == text.len() {
+                break;
+            }
+
+            let (result, consumed) = self.parse_escape_str(&text[end..]);
+            position = end + consumed;
+            if let Err(ansi_error) = result {
+                if position < text.len() {
+                    return Err(ansi_error);
+                }
+                if text.len() - end <= PENDING_LIMIT {
+                    self.pending = text[end..].to_string();
+                } else {
+                    // only drop the introducer, an ESC or a two byte C1 control
+                    position = end + text[end..].chars().next().map_or(1, char::len_utf8);
+                }
+            }
+        }
+        // runs on either side of an escape code are never adjacent in the input, so merging them copies
+        Ok(chain
+            .into_iter()
+            .coalesce(|(left_state, left_string), (right_state, right_string)| {
+                if left_state == right_state {
+                    Ok((
+                        left_state,
+                        Cow::Owned(left_string.into_owned() + &right_string),
+                    ))
+                } else {
+                    Err(((left_state, left_string), (right_state, right_string)))
+                }
+            })
+            .collect())
+    }
+
+    // `parse_str` for raw bytes, borrowing them as long as they are valid utf-8
+    // invalid sequences become U+FFFD, which means copying the runs
+    pub fn parse_bytes<'a>(&mut self, bytes: &'a [u8]) -> Result<BorrowedChain<'a>, AnsiError> {
+        match self.decode(bytes) {
+            Cow::Borrowed(text) => self.parse_str(text),
+            Cow::Owned(text) => Ok(self
+                .parse_str(&text)?
+                .into_iter()
+                .map(|(state, text)| (state, Cow::Owned(text.into_owned())))
+                .collect()),
+        }
+    }
+
+    // raw 8-bit C1 controls aren't utf-8, so with `c1_controls` on the lone bytes 0x80 to 0x9F are
+    // kept instead of becoming U+FFFD, those starting or ending escape codes in their 7-bit form
+    pub(crate) fn decode<'a>(&self, bytes: &'a [u8]) -> Cow<'a, str> {
+        if !self.c1_controls || core::str::from_utf8(bytes).is_ok() {
+            return String::from_utf8_lossy(bytes);
+        }
+        let mut text = String::with_capacity(bytes.len());
+        for chunk in bytes.utf8_chunks() {
+            text.push_str(chunk.valid());
+            match chunk.invalid() {
+                [] => {}
+                [byte @ (0x90 | 0x98 | 0x9b..=0x9f)] => {
+                    text.push('\x1b');
+                    text.push((byte - 0x40) as char);
+                }
+                [byte @ 0x80..=0x9f] => text.push(*byte as char),
+                _ => text.push(char::REPLACEMENT_CHARACTER),
+            }
+        }
+        Cow::Owned(text)
+    }
+
+    // the offset of the next ESC, or of the next C1 control starting or ending an escape code if enabled
+    fn find_escape(&self, bytes: &[u8]) -> Option<usize> {
+        if !self.c1_controls {
+            return memchr::memchr(b'\x1b', bytes);
+        }
+        memchr::memchr2_iter(b'\x1b', 0xc2, bytes).find(|&index| {
+            bytes[index] == b'\x1b'
+                || matches!(bytes.get(index + 1), Some(0x90 | 0x98 | 0x9b..=0x9f))
+        })
+    }
+
+    // parses the escape code at the start of `text`, returning how many bytes it took up
+    fn parse_escape_str(&mut self, text: &str) -> (Result<(), AnsiError>, usize) {
+        if let Some((parameters, final_byte)) = self.control_sequence(text) {
+            let result = self.current.apply_control_sequence(parameters, final_byte);
+            // the introducer is two bytes, `ESC [` or the utf-8 encoded CSI
+            return (result, 2 + parameters.len() + 1);
+        }
+        if self.c1_controls {
+            let mut characters = C1Controls::new(text.chars());
+            characters.next();
+            let result = self.parse_escape(&mut characters);
+            (result, text.len() - characters.into_inner().as_str().len())
+        } else {
+            let mut characters = text.chars();
+            characters.next();
+            let result = self.parse_escape(&mut characters);
+            (result, text.len() - characters.as_str().len())
+        }
+    }
+
+    // the parameters and final byte of a plain control sequence at the start of `text`, which
+    // are taken straight from the text. SGR stack operations and sequences with anything but
+    // parameter bytes before the final byte are left to `parse_escape`
+    fn control_sequence<'a>(&self, text: &'a str) -> Option<(&'a str, char)> {
+        let rest = text
+            .strip_prefix("\x1b[")
+            .or_else(|| text.strip_prefix('\u{9b}').filter(|_| self.c1_controls))?;
+        let end = rest
+            .bytes()
+            .position(|byte| !(0x20..=0x3f).contains(&byte))?;
+        let final_byte = rest.as_bytes()[end];
+        let parameters = &rest[..end];
+        ((0x40..=0x7e).contains(&final_byte) && !parameters.contains('#'))
+            .then_some((parameters, final_byte as char))
+    }
+
+    fn parse_chain<T: Iterator<Item = char> + Clone>(
+        &mut self,
+        characters: &mut T,
     ) -> Result<(), AnsiError> {
         let mut chain = Vec::new();
         loop {
             // get the text till the next escape code
-            let part: String = characters.take_while(|&c| c != '\x1b').collect();
-            if !part.is_empty() {
+            let mut escaped = false;
+            let part: String = characters
+                .take_while(|&c| {
+                    escaped = c == '\x1b';
+                    !escaped
+                })
+                .collect();
+            let part = self.translate_charsets(part);
+            if self.decode_overstrike && part.contains('\x08') {
+                chain.extend(parse_overstrike(&part, &self.current));
+            } else if !part.is_empty() {
                 chain.push((self.current.clone(), part))
             }
 
             // parse the escape code
-            match self.current.parse_ansi_code(characters) {
+            let escape = characters.clone();
+            match self.parse_escape(characters) {
                 Ok(()) => {}
                 Err(ansi_error) => {
                     if characters.next().is_some() {
                         return Err(ansi_error);
                     } else {
+                        // it may continue in the next input
+                        if escaped {
+                            let pending: String =
+                                core::iter::once('\x1b').chain(escape.clone()).collect();
+                            if pending.len() > PENDING_LIMIT {
+                                *characters = escape;
+                                continue;
+                            }
+                            self.pending = pending;
+                        }
                         break;
                     }
                 }
             }
         }
-        // find and fix duplicates
-        self.ansi_chain = chain
+        // find and fix duplicates, also with the end of the chain parsed before
+        let mut chain = chain
             .into_iter()
             .coalesce(|(left_state, left_string), (right_state, right_string)| {
                 if left_state == right_state {
@@ -54,14 +425,127 @@ impl Parser {
                     Err(((left_state, left_string), (right_state, right_string)))
                 }
             })
-            .collect();
+            .peekable();
+        if let (Some((last_state, last_string)), Some((first_state, _))) =
+            (self.ansi_chain.last_mut(), chain.peek())
+        {
+            if last_state == first_state {
+                last_string.push_str(&chain.next().expect("peeked").1);
+            }
+        }
+        self.ansi_chain.extend(chain);
         Ok(())
     }
+
+    fn parse_escape<T: Iterator<Item = char> + Clone>(
+        &mut self,
+        characters: &mut T,
+    ) -> Result<(), AnsiError> {
+        match characters.clone().next() {
+            Some('(' | ')') => {
+                let (slot, charset) =
+                    parse_charset_designation(characters).ok_or(AnsiError::InvalidFormat)?;
+                self.charsets[slot] = charset;
+                return Ok(());
+            }
+            Some(']' | 'P' | 'X' | '^' | '_' | '\\') => return skip_control_string(characters),
+            _ => {}
+        }
+        let mut lookahead = characters.clone();
+        match parse_sgr_stack_operation(&mut lookahead) {
+            Some(operation) => {
+                *characters = lookahead;
+                self.apply_sgr_stack_operation(operation);
+                Ok(())
+            }
+            None => self.current.parse_ansi_code(characters),
+        }
+    }
+
+    // applies the designated character sets and removes the shift in and shift out controls
+    fn translate_charsets(&mut self, part: String) -> String {
+        if !part.contains(['\x0e', '\x0f']) && self.charsets == [Charset::Ascii; 2] {
+            return part;
+        }
+        part.chars()
+            .filter_map(|c| match c {
+                '\x0e' => {
+                    self.shifted = true;
+                    None
+                }
+                '\x0f' => {
+                    self.shifted = false;
+                    None
+                }
+                c => Some(self.charsets[self.shifted as usize].translate(c)),
+            })
+            .collect()
+    }
+
+    fn apply_sgr_stack_operation(&mut self, operation: SgrStackOperation) {
+        match operation {
+            SgrStackOperation::Push(attributes) => {
+                if self.sgr_stack.len() < SGR_STACK_LIMIT {
+                    self.sgr_stack.push((self.current.clone(), attributes));
+                }
+            }
+            SgrStackOperation::Pop => {
+                if let Some((saved, attributes)) = self.sgr_stack.pop() {
+                    self.current.restore(saved, &attributes);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+// lets the html be written straight into an `io::Write`, keeping the io error `fmt::Error` can't carry
+struct IoAdapter<'a, W: io::Write> {
+    inner: &'a mut W,
+    error: io::Result<()>,
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> fmt::Write for IoAdapter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|error| {
+            self.error = Err(error);
+            fmt::Error
+        })
+    }
 }
 
+#[cfg(feature = "std")]
 pub(crate) struct Formatter {}
 
+#[cfg(feature = "std")]
 impl Formatter {
+    pub(crate) fn write_chain<W: fmt::Write>(chain: &AnsiChain, out: &mut W) -> fmt::Result {
+        out.write_str("<pre>")?;
+        for (state, text) in chain {
+            Formatter::write_run(state, text, out)?;
+        }
+        out.write_str("</pre>")
+    }
+
+    pub(crate) fn write_run<W: fmt::Write>(
+        state: &AnsiState,
+        text: &str,
+        out: &mut W,
+    ) -> fmt::Result {
+        if *state != AnsiState::default() {
+            write!(
+                out,
+                "<span style=\"{}\">{}</span>",
+                html_escape::encode_double_quoted_attribute(&state.to_style()),
+                html_escape::encode_text(text)
+            )
+        } else {
+            out.write_str(&html_escape::encode_text(text))
+        }
+    }
+
+    #[cfg(feature = "html")]
     pub(crate) fn format_chain(chain: AnsiChain) -> PreformattedText {
         let mut art = PreformattedText::builder();
         for (state, text) in chain {
@@ -78,18 +562,66 @@ impl Formatter {
     }
 }
 
-pub fn convert<T: Iterator<Item = char> + Clone>(
+#[cfg(feature = "std")]
+pub fn convert<T: Iterator<Item = char> + Clone>(characters: &mut T) -> Result<String, AnsiError> {
+    let mut parser = Parser::default();
+    parser.parse_ansi_text(characters)?;
+    Ok(parser.into_html())
+}
+
+#[cfg(feature = "html")]
+pub fn convert_to_preformatted<T: Iterator<Item = char> + Clone>(
     characters: &mut T,
 ) -> Result<PreformattedText, AnsiError> {
     let mut parser = Parser::default();
     parser.parse_ansi_text(characters)?;
-    Ok(Formatter::format_chain(parser.ansi_chain))
+    Ok(parser.into_preformatted())
+}
+
+// the visible text with every escape code removed
+pub fn strip<T: Iterator<Item = char> + Clone>(characters: &mut T) -> Result<String, AnsiError> {
+    let mut parser = Parser::default();
+    parser.parse_ansi_text(characters)?;
+    Ok(parser.plain_text())
+}
+
+pub fn attributed<T: Iterator<Item = char> + Clone>(
+    characters: &mut T,
+) -> Result<(String, StyledRanges), AnsiError> {
+    let mut parser = Parser::default();
+    parser.parse_ansi_text(characters)?;
+    Ok(parser.attributed_text())
+}
+
+#[cfg(feature = "std")]
+// reads html produced by `convert` back into the chain it was made from
+pub fn parse_html(html: &str) -> Result<AnsiChain, AnsiError> {
+    html_parser::parse_preformatted(html)
+}
+
+#[cfg(feature = "std")]
+pub fn html_to_ansi(html: &str) -> Result<String, AnsiError> {
+    Ok(Encoder::encode_chain(
+        &parse_html(html)?,
+        ColorDepth::TrueColor,
+    ))
+}
+
+// re-encodes ansi text with the fewest escape codes the given color depth allows
+pub fn normalize<T: Iterator<Item = char> + Clone>(
+    characters: &mut T,
+    depth: ColorDepth,
+) -> Result<String, AnsiError> {
+    let mut parser = Parser::default();
+    parser.parse_ansi_text(characters)?;
+    Ok(Encoder::encode_chain(&parser.ansi_chain, depth))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use color::Color;
+    use rstest::rstest;
     use state::{Blink, Intensity, InvertColors, Italics, Spacing, StrikeThrough, Underline};
     use std::iter::zip;
 
@@ -161,6 +693,416 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_text_with_pushed_sgr() {
+        let text = "\x1b[32mgreen \x1b[#{\x1b[1m\x1b[31mbold red \x1b[#}green again \
+            \x1b[30;31#{\x1b[34m\x1b[3mblue italic \x1b[#}\x1b[#}green italic";
+        let mut parser = Parser::default();
+        assert_eq!(parser.parse_ansi_text(&mut text.chars()), Ok(()));
+
+        let mut green = AnsiState::default();
+        green.parse_ansi_code(&mut "[32m".chars()).unwrap();
+        let mut bold_red = green.clone();
+        bold_red.parse_ansi_code(&mut "[1m".chars()).unwrap();
+        bold_red.parse_ansi_code(&mut "[31m".chars()).unwrap();
+        let mut blue_italic = green.clone();
+        blue_italic.parse_ansi_code(&mut "[34m".chars()).unwrap();
+        blue_italic.parse_ansi_code(&mut "[3m".chars()).unwrap();
+        let mut green_italic = green.clone();
+        green_italic.parse_ansi_code(&mut "[3m".chars()).unwrap();
+
+        let correct = vec![
+            (green.clone(), "green ".to_string()),
+            (bold_red, "bold red ".to_string()),
+            (green, "green again ".to_string()),
+            (blue_italic, "blue italic ".to_string()),
+            (green_italic, "green italic".to_string()),
+        ];
+        assert_eq!(parser.ansi_chain, correct);
+    }
+
+    #[test]
+    fn sgr_stack_is_bounded() {
+        let mut parser = Parser::default();
+        let text = "\x1b[#{".repeat(SGR_STACK_LIMIT + 5);
+        assert_eq!(parser.parse_ansi_text(&mut text.chars()), Ok(()));
+        assert_eq!(parser.sgr_stack.len(), SGR_STACK_LIMIT);
+    }
+
+    #[test]
+    fn parse_overstrike_with_ansi() {
+        let text = "\x1b[31mB\x08Bo\x08ol\x08ld\x08d red\x1b[0m and _\x08u_\x08l";
+        let mut red = AnsiState::default();
+        red.parse_ansi_code(&mut "[31m".chars()).unwrap();
+
+        let mut parser = Parser::default().decode_overstrike(true);
+        assert_eq!(parser.parse_ansi_text(&mut text.chars()), Ok(()));
+        let correct = vec![
+            (red.overstruck(true, false), "Bold".to_string()),
+            (red, " red".to_string()),
+            (AnsiState::default(), " and ".to_string()),
+            (
+                AnsiState::default().overstruck(false, true),
+                "ul".to_string(),
+            ),
+        ];
+        assert_eq!(parser.ansi_chain, correct);
+
+        let mut parser = Parser::default();
+        assert_eq!(parser.parse_ansi_text(&mut text.chars()), Ok(()));
+        assert_eq!(parser.ansi_chain.len(), 2);
+    }
+
+    #[test]
+    fn parse_dec_special_graphics() {
+        let text = "\x1b(0lqk\x1b(B\n\x1b[32mx\x1b[0mok\x1b(0x\n\x1b)0\x1b(Bmq\x0eqj\x0f end";
+        let mut green = AnsiState::default();
+        green.parse_ansi_code(&mut "[32m".chars()).unwrap();
+
+        let mut parser = Parser::default();
+        assert_eq!(parser.parse_ansi_text(&mut text.chars()), Ok(()));
+        let correct = vec![
+            (AnsiState::default(), "┌─┐\n".to_string()),
+            (green, "x".to_string()),
+            (AnsiState::default(), "ok│\nmq─┘ end".to_string()),
+        ];
+        assert_eq!(parser.ansi_chain, correct);
+    }
+
+    #[test]
+    fn parse_c1_controls() {
+        let text = "\u{9b}31mred\u{9b}0m \u{9d}0;title\u{9c}plain\x1b]0;title\x07 text";
+        let mut red = AnsiState::default();
+        red.parse_ansi_code(&mut "[31m".chars()).unwrap();
+
+        let mut parser = Parser::default().c1_controls(true);
+        assert_eq!(parser.parse_ansi_text(&mut text.chars()), Ok(()));
+        let correct = vec![
+            (red, "red".to_string()),
+            (AnsiState::default(), " plain text".to_string()),
+        ];
+        assert_eq!(parser.ansi_chain, correct);
+
+        let mut parser = Parser::default();
+        assert_eq!(parser.parse_ansi_text(&mut text.chars()), Ok(()));
+        assert_eq!(parser.ansi_chain.len(), 1);
+
+        // NEL and the other C1 controls that don't start escape codes stay text
+        let text = "a\u{85}b\u{9b}1m\u{80}";
+        let mut parser = Parser::default().c1_controls(true);
+        assert_eq!(parser.parse_ansi_text(&mut text.chars()), Ok(()));
+        let correct = vec![
+            (AnsiState::default(), "a\u{85}b".to_string()),
+            (AnsiState::from_code("[1m"), "\u{80}".to_string()),
+        ];
+        assert_eq!(parser.ansi_chain, correct);
+        let mut parser = Parser::default().c1_controls(true);
+        let borrowed: Vec<_> = parser
+            .parse_str(text)
+            .unwrap()
+            .into_iter()
+            .map(|(state, text)| (state, text.into_owned()))
+            .collect();
+        assert_eq!(borrowed, correct);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn ansi_html_ansi_round_trip() {
+        let text = "plain \x1b[1mbold\x1b[3m italic\x1b[4:3m curly\x1b[58;2;255;0;0m red curl\
+            \x1b[0m \x1b[2m\x1b[21m\x1b[5mfaint\x1b[6m\x1b[7m\x1b[9m\x1b[26m everything\
+            \x1b[0m\x1b[36m\x1b[41m <cyan & red>\x1b[38;2;1;2;3m\x1b[48;2;4;5;6m\x1b[4:4m full\
+            \x1b[4:5m\x1b[58;5;4m dashed\x1b[0m\n";
+        let mut parser = Parser::default();
+        assert_eq!(parser.parse_ansi_text(&mut text.chars()), Ok(()));
+        let chain = parser.ansi_chain.clone();
+
+        let html = parser.into_html();
+        assert_eq!(parse_html(&html), Ok(chain.clone()));
+
+        let ansi = html_to_ansi(&html).unwrap();
+        let mut parser = Parser::default();
+        assert_eq!(parser.parse_ansi_text(&mut ansi.chars()), Ok(()));
+        assert_eq!(parser.ansi_chain, chain);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn palette_colors_round_trip_exactly() {
+        let text = "\x1b[38;5;208morange \x1b[4;48;5;16;58;5;231mon black\x1b[0;91m bright\x1b[0m";
+        let html = convert(&mut text.chars()).unwrap();
+        assert_eq!(html_to_ansi(&html), Ok(text.to_string()));
+    }
+
+    #[test]
+    fn normalize_bloated_ansi() {
+        let text = "\x1b[0m\x1b[1m\x1b[31mred\x1b[0m\x1b[1m\x1b[31m still\x1b[0m\x1b[0m \x1b[38;2;0;0;255mblue";
+        assert_eq!(
+            normalize(&mut text.chars(), ColorDepth::TrueColor),
+            Ok("\x1b[1;31mred still\x1b[0m \x1b[38;2;0;0;255mblue\x1b[0m".to_string())
+        );
+        assert_eq!(
+            normalize(&mut text.chars(), ColorDepth::Palette16),
+            Ok("\x1b[1;31mred still\x1b[0m \x1b[34mblue\x1b[0m".to_string())
+        );
+    }
+
+    #[rstest]
+    #[case("plain text only", false, false)]
+    #[case(
+        "This is \x1b[32mgreen\x1b[0m and \x1b[1;4mbold\x1b[22m underlined\x1b[0m\n",
+        false,
+        false
+    )]
+    #[case(
+        "\x1b[31mred\x1b[0m\x1b[0m\x1b]0;title\x07 merged\x1b[0m again",
+        false,
+        false
+    )]
+    #[case("\x1b(0lqqk\x1b(B \x0exx\x0f done\x1b[1", false, false)]
+    #[case("N\x08NA\x08AM\x08ME\x08E \x1b[31m_\x08l\x1b[0ms", true, false)]
+    #[case("\u{9b}33mc1 \u{a0}\u{9d}0;title\u{9c}\x1b[0m\u{e9}", false, true)]
+    fn parse_str_matches_parse_ansi_text(
+        #[case] text: &str,
+        #[case] overstrike: bool,
+        #[case] c1_controls: bool,
+    ) {
+        let make_parser = || {
+            Parser::default()
+                .decode_overstrike(overstrike)
+                .c1_controls(c1_controls)
+        };
+        let mut parser = make_parser();
+        parser.parse_ansi_text(&mut text.chars()).unwrap();
+        let borrowed: AnsiChain = make_parser()
+            .parse_str(text)
+            .unwrap()
+            .into_iter()
+            .map(|(state, text)| (state, text.into_owned()))
+            .collect();
+        assert_eq!(&borrowed, parser.ansi_chain());
+    }
+
+    #[test]
+    fn parse_str_borrows_text() {
+        let text = "\x1b[1mbold\x1b[0m plain";
+        let chain = Parser::default().parse_str(text).unwrap();
+        assert!(chain
+            .iter()
+            .all(|(_, text)| matches!(text, Cow::Borrowed(_))));
+        assert_eq!(chain[1].1, " plain");
+        assert_eq!(
+            Parser::default().parse_str("\x1b[2Jcleared\x1b[K"),
+            Ok(vec![(AnsiState::default(), Cow::Borrowed("cleared"))])
+        );
+        assert_eq!(
+            Parser::default().parse_str("\x1b[1:3mx"),
+            Err(AnsiError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn parse_bytes_borrows_valid_utf8() {
+        let chain = Parser::default()
+            .parse_bytes(b"\x1b[1mbold\x1b[0m plain")
+            .unwrap();
+        assert!(chain
+            .iter()
+            .all(|(_, text)| matches!(text, Cow::Borrowed(_))));
+        assert_eq!(
+            Parser::default().parse_bytes(b"\x1b[1mbad \xff\x1b[0m"),
+            Ok(vec![(
+                AnsiState::from_code("[1m"),
+                Cow::Owned("bad \u{fffd}".to_string())
+            )])
+        );
+    }
+
+    #[test]
+    fn parse_raw_c1_bytes() {
+        let bytes = b"\x9b31mred\x9b0m \x9d0;title\x9c\x85\xff\xc3\xa9";
+        assert_eq!(
+            Parser::default().c1_controls(true).parse_bytes(bytes),
+            Ok(vec![
+                (AnsiState::from_code("[31m"), Cow::Owned("red".to_string())),
+                (
+                    AnsiState::default(),
+                    Cow::Owned(" \u{85}\u{fffd}\u{e9}".to_string())
+                ),
+            ])
+        );
+        assert_eq!(
+            Parser::default().parse_bytes(b"\x9b31mred"),
+            Ok(vec![(
+                AnsiState::default(),
+                Cow::Owned("\u{fffd}31mred".to_string())
+            )])
+        );
+    }
+
+    #[test]
+    fn parse_in_pieces() {
+        let text = "plain \x1b[1mbold \x1b[#{\x1b[38;2;1;2;3mfull\x1b[#} \u{e9}\x1b]0;t\x07\x1b(0q\x1b(B\x1b[0m end";
+        let mut whole = Parser::default();
+        assert_eq!(whole.parse_ansi_text(&mut text.chars()), Ok(()));
+        for (split, _) in text.char_indices() {
+            let (left, right) = text.split_at(split);
+            let mut parser = Parser::default();
+            assert_eq!(parser.parse_ansi_text(&mut left.chars()), Ok(()));
+            let mut deltas = parser.take_chain();
+            assert_eq!(parser.parse_ansi_text(&mut right.chars()), Ok(()));
+            deltas.extend(parser.take_chain());
+            assert_eq!(
+                deltas.concat_runs(),
+                whole.ansi_chain.concat_runs(),
+                "split at {}",
+                split
+            );
+
+            let mut parser = Parser::default();
+            assert_eq!(parser.parse_ansi_text(&mut left.chars()), Ok(()));
+            assert_eq!(parser.parse_ansi_text(&mut right.chars()), Ok(()));
+            assert_eq!(parser.ansi_chain, whole.ansi_chain);
+
+            let mut parser = Parser::default();
+            let mut borrowed = parser.parse_str(left).unwrap();
+            borrowed.extend(parser.parse_str(right).unwrap());
+            let borrowed: AnsiChain = borrowed
+                .into_iter()
+                .map(|(state, text)| (state, text.into_owned()))
+                .collect();
+            assert_eq!(borrowed.concat_runs(), whole.ansi_chain.concat_runs());
+        }
+    }
+
+    #[test]
+    fn give_up_on_unterminated_control_strings() {
+        let lines = "log line\n".repeat(200);
+        let mut parser = Parser::default();
+        let mut borrowed = Parser::default();
+        let mut shown = String::new();
+        assert_eq!(
+            parser.parse_ansi_text(&mut "ok \x1b]0;title".chars()),
+            Ok(())
+        );
+        for (_, text) in borrowed.parse_str("ok \x1b]0;title").unwrap() {
+            shown.push_str(&text);
+        }
+        for _ in 0..3 {
+            assert_eq!(parser.parse_ansi_text(&mut lines.chars()), Ok(()));
+            for (_, text) in borrowed.parse_str(&lines).unwrap() {
+                shown.push_str(&text);
+            }
+            assert!(parser.pending.len() <= PENDING_LIMIT);
+            assert!(borrowed.pending.len() <= PENDING_LIMIT);
+        }
+        // once given up on, only the ESC is dropped and everything it held back comes through
+        assert_eq!(
+            parser.plain_text(),
+            format!("ok ]0;title{}", lines.repeat(3))
+        );
+        assert_eq!(shown, parser.plain_text());
+    }
+
+    #[test]
+    fn resume_from_state() {
+        let mut first = Parser::default();
+        assert_eq!(first.parse_ansi_text(&mut "\x1b[1;31mred".chars()), Ok(()));
+        let mut second = Parser::with_state(first.current_state().clone());
+        assert_eq!(
+            second.parse_ansi_text(&mut " still\x1b[22m".chars()),
+            Ok(())
+        );
+        assert_eq!(
+            second.ansi_chain,
+            vec![(first.current.clone(), " still".to_string())]
+        );
+        assert_eq!(
+            second.current_state(),
+            &AnsiState {
+                text_color: Color::Red,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[rstest]
+    #[case("", "0")]
+    #[case("0", "0")]
+    #[case("1;31", "0;1;31")]
+    #[case("4:3;38;5;208;48;2;1;2;3", "0;4:3;38;5;208;48;2;1;2;3")]
+    fn store_state_as_sgr(#[case] sgr: &str, #[case] stored: &str) {
+        let state: AnsiState = sgr.parse().unwrap();
+        assert_eq!(state.to_string(), stored);
+        assert_eq!(stored.parse(), Ok(state));
+    }
+
+    #[rstest]
+    #[case("1m31")]
+    #[case("\x1b[1m")]
+    #[case("31;red")]
+    fn invalid_stored_state(#[case] sgr: &str) {
+        assert!(sgr.parse::<AnsiState>().is_err());
+    }
+
+    // merges runs of the same style, as chains parsed in pieces may split them
+    trait ConcatRuns {
+        fn concat_runs(&self) -> AnsiChain;
+    }
+
+    impl ConcatRuns for AnsiChain {
+        fn concat_runs(&self) -> AnsiChain {
+            self.iter()
+                .cloned()
+                .coalesce(|(left_state, left_string), (right_state, right_string)| {
+                    if left_state == right_state {
+                        Ok((left_state, left_string + &right_string))
+                    } else {
+                        Err(((left_state, left_string), (right_state, right_string)))
+                    }
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn strip_escape_codes() {
+        let text = "\x1b[1mwarning\x1b[0m: \x1b]0;title\x07unused \x1b[38;5;196mvariable\x1b[m";
+        assert_eq!(
+            strip(&mut text.chars()),
+            Ok("warning: unused variable".to_string())
+        );
+    }
+
+    #[test]
+    fn attributed_text_ranges() {
+        let text = "h\u{e9}llo \x1b[31mw\x1b[1morld\x1b[0m!";
+        let (plain, ranges) = attributed(&mut text.chars()).unwrap();
+        assert_eq!(plain, "h\u{e9}llo world!");
+        let red = AnsiState {
+            text_color: Color::Red,
+            ..Default::default()
+        };
+        let bold_red = AnsiState {
+            intensity: Intensity::Bold,
+            ..red.clone()
+        };
+        assert_eq!(
+            ranges,
+            vec![
+                (0..7, AnsiState::default()),
+                (7..8, red),
+                (8..12, bold_red),
+                (12..13, AnsiState::default()),
+            ]
+        );
+        assert_eq!(&plain[ranges[2].0.clone()], "orld");
+        assert_eq!(ranges[2].1.intensity(), Intensity::Bold);
+        assert_eq!(ranges[2].1.text_color(), Color::Red);
+        assert_eq!(ranges[2].1.underline(), Underline::None);
+    }
+
+    #[cfg(feature = "std")]
     #[test]
     fn make_html_from_chain() {
         let chain = vec![
@@ -183,6 +1125,27 @@ mod tests {
         ];
         let correct =
             "<pre>This is default text<span style=\"font-weight:bold;\">and this text is bold</span></pre>";
+        let mut html = String::new();
+        assert_eq!(Formatter::write_chain(&chain, &mut html), Ok(()));
+        assert_eq!(html, correct);
+        #[cfg(feature = "html")]
         assert_eq!(Formatter::format_chain(chain).to_string(), correct);
     }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn write_html_to_io() {
+        let mut parser = Parser::default();
+        assert_eq!(
+            parser.parse_ansi_text(&mut "a < b \x1b[31mred\x1b[0m".chars()),
+            Ok(())
+        );
+        let mut bytes = Vec::new();
+        parser.write_html_io(&mut bytes).unwrap();
+        assert_eq!(
+            String::from_utf8(bytes).unwrap(),
+            "<pre>a &lt; b <span style=\"color:red;\">red</span></pre>"
+        );
+        assert!(parser.write_html_io(&mut &mut [0u8; 8][..]).is_err());
+    }
 }
@@ -0,0 +1,154 @@
+use std::{borrow::Cow, sync::Mutex, thread};
+
+use crate::{error::AnsiError, index::LineStart, state::AnsiState, Formatter, Parser};
+
+const CHUNKS_PER_THREAD: usize = 4;
+
+// the first and last run of a chunk, which may merge with its neighbours, and the html of the runs in between
+type Chunk<'a> = (
+    Option<(AnsiState, Cow<'a, str>)>,
+    String,
+    Option<(AnsiState, Cow<'a, str>)>,
+);
+
+impl Parser {
+    // converts `text` to html on at most `threads` threads, giving exactly what `parse_ansi_text` and `into_html` would
+    pub fn convert_parallel(&self, text: &str, threads: usize) -> Result<String, AnsiError> {
+        let threads = threads.max(1);
+        // a few chunks per thread, so a thread that finishes early can take over more of the text
+        let step = text.len() / (threads * CHUNKS_PER_THREAD) + 1;
+        let mut target = step;
+        let checkpoints = self.line_starts(text, |offset, _| {
+            if offset < target {
+                return LineStart::Skip;
+            }
+            target = offset + step;
+            LineStart::Keep
+        })?;
+        let ends: Vec<usize> = checkpoints
+            .iter()
+            .skip(1)
+            .map(|(offset, _, _)| *offset)
+            .chain([text.len()])
+            .collect();
+        let count = checkpoints.len();
+        let queue = Mutex::new(checkpoints.into_iter().zip(ends).enumerate());
+        let mut chunks: Vec<(usize, Result<Chunk, AnsiError>)> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads.min(count))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut done = Vec::new();
+                        loop {
+                            let next = queue.lock().expect("a conversion thread panicked").next();
+                            let Some((index, ((start, _, mut parser), end))) = next else {
+                                return done;
+                            };
+                            done.push((index, convert_chunk(&mut parser, &text[start..end])));
+                        }
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().expect("conversion thread panicked"))
+                .collect()
+        });
+        chunks.sort_unstable_by_key(|(index, _)| *index);
+        let chunks = chunks.into_iter().map(|(_, chunk)| chunk);
+
+        // stitch the chunks together, merging runs that continue over a split like the sequential parser does
+        let mut html = String::from("<pre>");
+        let mut pending: Option<(AnsiState, String)> = None;
+        let flush = |html: &mut String, (state, text): (AnsiState, String)| {
+            Formatter::write_run(&state, &text, html).expect("writing to a String can't fail");
+        };
+        for chunk in chunks {
+            let (Some((state, text)), middle, last) = chunk? else {
+                continue;
+            };
+            pending = match pending.take() {
+                Some((pending_state, mut pending_text)) if pending_state == state => {
+                    pending_text.push_str(&text);
+                    Some((pending_state, pending_text))
+                }
+                Some(run) => {
+                    flush(&mut html, run);
+                    Some((state, text.into_owned()))
+                }
+                None => Some((state, text.into_owned())),
+            };
+            if let Some((state, text)) = last {
+                if let Some(run) = pending.take() {
+                    flush(&mut html, run);
+                }
+                html.push_str(&middle);
+                pending = Some((state, text.into_owned()));
+            }
+        }
+        if let Some(run) = pending {
+            flush(&mut html, run);
+        }
+        html.push_str("</pre>");
+        Ok(html)
+    }
+}
+
+fn convert_chunk<'a>(parser: &mut Parser, text: &'a str) -> Result<Chunk<'a>, AnsiError> {
+    let mut chain = parser.parse_str(text)?.into_iter();
+    let first = chain.next();
+    let last = chain.next_back();
+    let mut middle = String::new();
+    for (state, text) in chain {
+        Formatter::write_run(&state, &text, &mut middle).expect("writing to a String can't fail");
+    }
+    Ok((first, middle, last))
+}
+
+pub fn convert_parallel(text: &str) -> Result<String, AnsiError> {
+    let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+    Parser::default().convert_parallel(text, threads)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::log;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(0)]
+    #[case(1)]
+    #[case(2)]
+    #[case(3)]
+    #[case(8)]
+    #[case(1000)]
+    fn parallel_output_matches_sequential(#[case] threads: usize) {
+        // a control string over two lines, which a chunk can start inside of
+        let log = format!(
+            "{}\x1b]0;a title\nover two lines\x07\n{}",
+            log(100),
+            log(100)
+        );
+        for (overstrike, c1_controls) in [(false, false), (true, false), (false, true)] {
+            let parser = Parser::default()
+                .decode_overstrike(overstrike)
+                .c1_controls(c1_controls);
+            let mut sequential = parser.clone();
+            sequential.parse_ansi_text(&mut log.chars()).unwrap();
+            assert_eq!(
+                parser.convert_parallel(&log, threads),
+                Ok(sequential.into_html())
+            );
+        }
+    }
+
+    #[test]
+    fn parallel_conversion_reports_errors() {
+        let log = format!("{}\x1b[1:3m\n{}", log(200), log(200));
+        assert_eq!(
+            Parser::default().convert_parallel(&log, 4),
+            Err(AnsiError::InvalidFormat)
+        );
+        assert_eq!(convert_parallel(""), Ok("<pre></pre>".to_string()));
+    }
+}
@@ -0,0 +1,288 @@
+// the python module, built with `maturin build` from the settings in pyproject.toml:
+//
+//     import anstml
+//     html = anstml.convert(output, decode_overstrike=True)
+//     converter = anstml.Converter()
+//     html = converter.feed(b"\x1b[31mfail") + converter.feed("ed") + converter.finish()
+//     [run.style.foreground for run in anstml.parse(output)]
+
+use pyo3::{create_exception, exceptions::PyValueError, prelude::*};
+
+use crate::{
+    color::Color,
+    error::AnsiError,
+    state::{
+        AnsiState, Blink, Intensity, InvertColors, Italics, Script, Spacing, StrikeThrough,
+        Underline,
+    },
+    HtmlConverter, Parser,
+};
+
+create_exception!(anstml, ParseError, PyValueError);
+
+impl From<AnsiError> for PyErr {
+    fn from(error: AnsiError) -> Self {
+        ParseError::new_err(format!("{error:?}"))
+    }
+}
+
+// the same forms as the json schema: None, a palette slot or a palette name or "#RRGGBB"
+#[derive(IntoPyObject)]
+enum PyColor {
+    Slot(u8),
+    Name(String),
+}
+
+fn color_of(color: &Color) -> Option<PyColor> {
+    match color {
+        Color::None => None,
+        Color::Byte(n) => Some(PyColor::Slot(*n)),
+        _ => Some(PyColor::Name(color.to_string())),
+    }
+}
+
+#[derive(FromPyObject)]
+enum Input {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+fn parser_of(decode_overstrike: bool, c1_controls: bool) -> Parser {
+    Parser::default()
+        .decode_overstrike(decode_overstrike)
+        .c1_controls(c1_controls)
+}
+
+#[pyfunction]
+#[pyo3(signature = (text, *, decode_overstrike = false, c1_controls = false))]
+fn convert(
+    py: Python<'_>,
+    text: String,
+    decode_overstrike: bool,
+    c1_controls: bool,
+) -> PyResult<String> {
+    let html = py.detach(|| {
+        let mut parser = parser_of(decode_overstrike, c1_controls);
+        parser.parse_ansi_text(&mut text.chars())?;
+        Ok::<_, AnsiError>(parser.into_html())
+    })?;
+    Ok(html)
+}
+
+#[pyfunction]
+#[pyo3(signature = (text, *, decode_overstrike = false, c1_controls = false))]
+fn parse(text: &str, decode_overstrike: bool, c1_controls: bool) -> PyResult<Vec<Run>> {
+    let mut parser = parser_of(decode_overstrike, c1_controls);
+    parser.parse_ansi_text(&mut text.chars())?;
+    Ok(parser
+        .take_chain()
+        .into_iter()
+        .map(|(state, text)| Run {
+            text,
+            style: Style(state),
+        })
+        .collect())
+}
+
+#[pyclass(module = "anstml", frozen, eq, get_all)]
+#[derive(Debug, PartialEq, Clone)]
+struct Run {
+    text: String,
+    style: Style,
+}
+
+#[pymethods]
+impl Run {
+    fn __repr__(&self) -> String {
+        format!("Run({:?}, {})", self.text, self.style.__repr__())
+    }
+}
+
+#[pyclass(module = "anstml", frozen, eq)]
+#[derive(Debug, PartialEq, Clone)]
+struct Style(AnsiState);
+
+#[pymethods]
+impl Style {
+    #[getter]
+    fn foreground(&self) -> Option<PyColor> {
+        color_of(&self.0.text_color)
+    }
+
+    #[getter]
+    fn background(&self) -> Option<PyColor> {
+        color_of(&self.0.background_color)
+    }
+
+    #[getter]
+    fn underline_color(&self) -> Option<PyColor> {
+        color_of(&self.0.underline_color)
+    }
+
+    #[getter]
+    fn bold(&self) -> bool {
+        self.0.intensity == Intensity::Bold
+    }
+
+    #[getter]
+    fn faint(&self) -> bool {
+        self.0.intensity == Intensity::Faint
+    }
+
+    #[getter]
+    fn italic(&self) -> bool {
+        self.0.italic == Italics::Yes
+    }
+
+    #[getter]
+    fn underline(&self) -> &'static str {
+        match self.0.underline {
+            Underline::None => "none",
+            Underline::Single => "single",
+            Underline::Double => "double",
+            Underline::Curly => "curly",
+            Underline::Dotted => "dotted",
+            Underline::Dashed => "dashed",
+        }
+    }
+
+    #[getter]
+    fn blink(&self) -> bool {
+        self.0.blink == Blink::Slow
+    }
+
+    #[getter]
+    fn rapid_blink(&self) -> bool {
+        self.0.blink == Blink::Fast
+    }
+
+    #[getter]
+    fn inverse(&self) -> bool {
+        self.0.invert_colors == InvertColors::Yes
+    }
+
+    #[getter]
+    fn strikethrough(&self) -> bool {
+        self.0.strikethrough == StrikeThrough::Yes
+    }
+
+    #[getter]
+    fn proportional(&self) -> bool {
+        self.0.spacing == Spacing::Proportional
+    }
+
+    #[getter]
+    fn superscript(&self) -> bool {
+        self.0.script == Script::Super
+    }
+
+    #[getter]
+    fn subscript(&self) -> bool {
+        self.0.script == Script::Sub
+    }
+
+    // the declarations `convert` puts in the style attribute of the span
+    fn css(&self) -> String {
+        self.0.to_style()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Style({:?})", self.0.to_style())
+    }
+}
+
+#[pyclass(module = "anstml")]
+struct Converter(Option<HtmlConverter>);
+
+impl Converter {
+    fn converter(&mut self) -> PyResult<&mut HtmlConverter> {
+        self.0
+            .as_mut()
+            .ok_or_else(|| PyValueError::new_err("the converter is already finished"))
+    }
+}
+
+#[pymethods]
+impl Converter {
+    #[new]
+    #[pyo3(signature = (*, decode_overstrike = false, c1_controls = false))]
+    fn new(decode_overstrike: bool, c1_controls: bool) -> Self {
+        Converter(Some(HtmlConverter::new(parser_of(
+            decode_overstrike,
+            c1_controls,
+        ))))
+    }
+
+    // takes str or utf-8 bytes, which may end in the middle of a character
+    fn feed(&mut self, data: Input) -> PyResult<String> {
+        let converter = self.converter()?;
+        Ok(match data {
+            Input::Text(text) => converter.feed(text.as_bytes()),
+            Input::Bytes(bytes) => converter.feed(&bytes),
+        }?)
+    }
+
+    fn finish(&mut self) -> PyResult<String> {
+        self.converter()?;
+        Ok(self.0.take().unwrap().finish()?)
+    }
+}
+
+#[pymodule]
+fn anstml(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(convert, module)?)?;
+    module.add_function(wrap_pyfunction!(parse, module)?)?;
+    module.add_class::<Converter>()?;
+    module.add_class::<Run>()?;
+    module.add_class::<Style>()?;
+    module.add("ParseError", module.py().get_type::<ParseError>())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::types::IntoPyDict;
+    use rstest::rstest;
+    use std::ffi::CStr;
+
+    #[rstest]
+    #[case(cr##"assert anstml.convert("a\x1b[1mb") == '<pre>a<span style="font-weight:bold;">b</span></pre>'"##)]
+    #[case(cr##"assert anstml.convert("N\bN", decode_overstrike=True) == '<pre><span style="font-weight:bold;">N</span></pre>'"##)]
+    #[case(
+        cr##"
+assert anstml.convert("\x1b[2Jx") == "<pre>x</pre>"
+try:
+    anstml.convert("\x1b[1:3mx")
+    assert False
+except anstml.ParseError as error:
+    assert isinstance(error, ValueError)
+"##
+    )]
+    #[case(cr##"
+converter = anstml.Converter()
+html = converter.feed(b"\x1b[31m\xc3") + converter.feed(b"\xa9") + converter.feed("!") + converter.finish()
+assert html == '<pre><span style="color:red;">é</span><span style="color:red;">!</span></pre>', html
+try:
+    converter.feed("again")
+    assert False
+except ValueError:
+    pass
+"##)]
+    #[case(cr##"
+runs = anstml.parse("a\x1b[1;4:3;38;5;208;48;2;1;2;3mb")
+assert [run.text for run in runs] == ["a", "b"]
+assert runs[0].style.foreground is None and not runs[0].style.bold
+style = runs[1].style
+assert (style.foreground, style.background, style.bold, style.underline) == (208, "#010203", True, "curly")
+assert runs == anstml.parse("a\x1b[1;4:3;38;5;208;48;2;1;2;3mb")
+"##)]
+    fn python_module(#[case] code: &CStr) {
+        Python::initialize();
+        Python::attach(|py| {
+            let globals = [("anstml", pyo3::wrap_pymodule!(anstml)(py))].into_py_dict(py)?;
+            py.run(code, Some(&globals), None)
+        })
+        .unwrap();
+    }
+}
@@ -0,0 +1,316 @@
+use font8x8::{
+    UnicodeFonts, BASIC_FONTS, BLOCK_FONTS, BOX_FONTS, GREEK_FONTS, HIRAGANA_FONTS, LATIN_FONTS,
+    MISC_FONTS,
+};
+
+use crate::{
+    color::Color,
+    error::AnsiError,
+    state::{AnsiState, Intensity, Italics, StrikeThrough, Underline},
+    AnsiChain,
+};
+
+type Rgb = (u8, u8, u8);
+
+// a row of padding above the 8x8 glyph, and three rows below it for the underlines
+const CELL_WIDTH: usize = 8;
+const CELL_HEIGHT: usize = 12;
+const TAB_WIDTH: usize = 8;
+
+pub struct PngRenderer {
+    scale: u32,
+    padding: u32,
+    foreground: Rgb,
+    background: Rgb,
+}
+
+impl Default for PngRenderer {
+    fn default() -> Self {
+        PngRenderer {
+            scale: 2,
+            padding: 4,
+            foreground: (0xD0, 0xD0, 0xD0),
+            background: (0x1C, 0x1C, 0x1C),
+        }
+    }
+}
+
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb>,
+}
+
+impl Canvas {
+    fn set(&mut self, x: usize, y: usize, color: Rgb) {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x] = color;
+        }
+    }
+}
+
+impl PngRenderer {
+    pub fn scale(mut self, scale: u32) -> Self {
+        self.scale = scale.max(1);
+        self
+    }
+
+    // the padding around the text in unscaled pixels
+    pub fn padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn colors(mut self, foreground: Rgb, background: Rgb) -> Self {
+        self.foreground = foreground;
+        self.background = background;
+        self
+    }
+
+    pub fn render(&self, chain: &AnsiChain) -> Result<Vec<u8>, AnsiError> {
+        let mut rows: Vec<Vec<(char, &AnsiState)>> = vec![Vec::new()];
+        let mut column = 0;
+        for (state, text) in chain {
+            for c in text.chars() {
+                let row = rows.last_mut().unwrap();
+                match c {
+                    '\n' => {
+                        rows.push(Vec::new());
+                        column = 0;
+                    }
+                    // like a terminal, later text on the row draws over what was there
+                    '\r' => column = 0,
+                    // tabs move to the next stop and only fill cells that are still empty
+                    '\t' => {
+                        column = (column / TAB_WIDTH + 1) * TAB_WIDTH;
+                        while row.len() < column {
+                            row.push((' ', state));
+                        }
+                    }
+                    c => {
+                        match row.get_mut(column) {
+                            Some(cell) => *cell = (c, state),
+                            None => row.push((c, state)),
+                        }
+                        column += 1;
+                    }
+                }
+            }
+        }
+        // output ending in a newline gets no empty row below it, the same as in `SvgRenderer`
+        if rows.len() > 1 && rows.last().is_some_and(|row| row.is_empty()) {
+            rows.pop();
+        }
+
+        let padding = self.padding as usize;
+        let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        let mut canvas = Canvas {
+            width: (columns * CELL_WIDTH + 2 * padding).max(1),
+            height: (rows.len() * CELL_HEIGHT + 2 * padding).max(1),
+            pixels: Vec::new(),
+        };
+        canvas.pixels = vec![self.background; canvas.width * canvas.height];
+
+        // the backgrounds go first, so bold and italic glyphs can overhang into the next cell
+        for (y, row) in rows.iter().enumerate() {
+            for (x, (_, state)) in row.iter().enumerate() {
+                let (_, background) = self.colors_of(state);
+                if background != self.background {
+                    for dy in 0..CELL_HEIGHT {
+                        for dx in 0..CELL_WIDTH {
+                            canvas.set(
+                                padding + x * CELL_WIDTH + dx,
+                                padding + y * CELL_HEIGHT + dy,
+                                background,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        for (y, row) in rows.iter().enumerate() {
+            for (x, (c, state)) in row.iter().enumerate() {
+                self.draw_cell(
+                    &mut canvas,
+                    padding + x * CELL_WIDTH,
+                    padding + y * CELL_HEIGHT,
+                    *c,
+                    state,
+                );
+            }
+        }
+        self.encode(&canvas)
+    }
+
+    fn colors_of(&self, state: &AnsiState) -> (Rgb, Rgb) {
+        let (foreground, background) =
+            state.effective_colors(Color::to_rgb, self.foreground, self.background);
+        let foreground = foreground.unwrap_or(self.foreground);
+        let background = background.unwrap_or(self.background);
+        match state.intensity {
+            Intensity::Faint => (blend(foreground, background), background),
+            _ => (foreground, background),
+        }
+    }
+
+    fn draw_cell(&self, canvas: &mut Canvas, left: usize, top: usize, c: char, state: &AnsiState) {
+        let (foreground, _) = self.colors_of(state);
+        for (row, bits) in glyph(c).iter().enumerate() {
+            // lean the top of the glyph to the right
+            let slant = match state.italic {
+                Italics::Yes => (7 - row) / 3,
+                Italics::No => 0,
+            };
+            for column in (0..8).filter(|column| bits & (1 << column) != 0) {
+                canvas.set(left + column + slant, top + 1 + row, foreground);
+                if state.intensity == Intensity::Bold {
+                    canvas.set(left + column + slant + 1, top + 1 + row, foreground);
+                }
+            }
+        }
+        if state.strikethrough == StrikeThrough::Yes {
+            for dx in 0..CELL_WIDTH {
+                canvas.set(left + dx, top + 5, foreground);
+            }
+        }
+        let line = state.underline_color.to_rgb().unwrap_or(foreground);
+        for dx in 0..CELL_WIDTH {
+            let rows: &[usize] = match state.underline {
+                Underline::None => &[],
+                Underline::Single => &[10],
+                Underline::Double => &[9, 11],
+                Underline::Curly if (left + dx) % 4 < 2 => &[10],
+                Underline::Curly => &[11],
+                Underline::Dotted if (left + dx).is_multiple_of(2) => &[10],
+                Underline::Dashed if (left + dx) % 4 != 3 => &[10],
+                Underline::Dotted | Underline::Dashed => &[],
+            };
+            for row in rows {
+                canvas.set(left + dx, top + row, line);
+            }
+        }
+    }
+
+    fn encode(&self, canvas: &Canvas) -> Result<Vec<u8>, AnsiError> {
+        let scale = self.scale as usize;
+        let mut data = Vec::with_capacity(canvas.pixels.len() * scale * scale * 3);
+        for row in canvas.pixels.chunks(canvas.width) {
+            for _ in 0..scale {
+                for &(r, g, b) in row {
+                    for _ in 0..scale {
+                        data.extend([r, g, b]);
+                    }
+                }
+            }
+        }
+
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(
+            &mut png,
+            (canvas.width * scale) as u32,
+            (canvas.height * scale) as u32,
+        );
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        // the data always fits the dimensions, so only an image too big for png can fail here
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&data))
+            .map_err(|_| AnsiError::Encode)?;
+        Ok(png)
+    }
+}
+
+fn blend((r, g, b): Rgb, (br, bg, bb): Rgb) -> Rgb {
+    let half = |c: u8, b: u8| ((c as u16 + b as u16) / 2) as u8;
+    (half(r, br), half(g, bg), half(b, bb))
+}
+
+// characters the bundled font doesn't have are drawn as a question mark
+fn glyph(c: char) -> [u8; 8] {
+    BASIC_FONTS
+        .get(c)
+        .or_else(|| LATIN_FONTS.get(c))
+        .or_else(|| BOX_FONTS.get(c))
+        .or_else(|| BLOCK_FONTS.get(c))
+        .or_else(|| GREEK_FONTS.get(c))
+        .or_else(|| HIRAGANA_FONTS.get(c))
+        .or_else(|| MISC_FONTS.get(c))
+        .or_else(|| BASIC_FONTS.get('?'))
+        .unwrap_or([0; 8])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(png: &[u8]) -> (u32, u32, Vec<u8>) {
+        let mut reader = png::Decoder::new(png).read_info().unwrap();
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data).unwrap();
+        (info.width, info.height, data)
+    }
+
+    #[test]
+    fn render_cells_with_scale() {
+        let chain = vec![
+            (AnsiState::default(), "ab\n".to_string()),
+            (AnsiState::from_code("[4;41;58;2;0;0;255m"), "c".to_string()),
+        ];
+        let png = PngRenderer::default()
+            .scale(3)
+            .padding(1)
+            .colors((255, 255, 255), (0, 0, 0))
+            .render(&chain)
+            .unwrap();
+        let (width, height, data) = decode(&png);
+        assert_eq!((width, height), (3 * (2 * 8 + 2), 3 * (2 * 12 + 2)));
+
+        let pixel = |x: usize, y: usize| {
+            let i = (y * 3 * width as usize + x * 3) * 3;
+            (data[i], data[i + 1], data[i + 2])
+        };
+        // the padding, the red background and the blue underline of `c`
+        assert_eq!(pixel(0, 0), (0, 0, 0));
+        assert_eq!(pixel(1, 13), (205, 0, 0));
+        assert_eq!(pixel(1, 13 + 10), (0, 0, 255));
+    }
+
+    #[test]
+    fn expand_tabs_and_return_carriage() {
+        let render = |text: &str| {
+            let chain = vec![(AnsiState::from_code("[41m"), text.to_string())];
+            decode(
+                &PngRenderer::default()
+                    .scale(1)
+                    .padding(0)
+                    .render(&chain)
+                    .unwrap(),
+            )
+        };
+        let (width, _, data) = render("a\tb");
+        assert_eq!(width, 9 * CELL_WIDTH as u32);
+        // the cells the tab skips over get the background as well
+        let cell = 5 * CELL_WIDTH * 3;
+        assert_eq!(&data[cell..cell + 3], &[205, 0, 0]);
+
+        assert_eq!(
+            render("progress 10%\rprogress 100%\r\n"),
+            render("progress 100%")
+        );
+        assert_eq!(render("abc\rx"), render("xbc"));
+    }
+
+    #[test]
+    fn empty_chain_still_makes_an_image() {
+        let (width, height, _) =
+            decode(&PngRenderer::default().padding(0).render(&vec![]).unwrap());
+        assert_eq!((width, height), (2, 2 * CELL_HEIGHT as u32));
+    }
+
+    #[test]
+    fn unknown_glyphs_fall_back() {
+        assert_eq!(glyph('\u{10FFFF}'), glyph('?'));
+        assert_ne!(glyph('─'), glyph('?'));
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{
+    color::Color,
+    state::{AnsiState, Intensity, Italics, StrikeThrough, Underline},
+    AnsiChain,
+};
+
+// neither toolkit can invert colors, so inverted runs swap them and fill in the defaults
+fn colors_of(
+    state: &AnsiState,
+    foreground: &str,
+    background: &str,
+) -> (Option<String>, Option<String>) {
+    state.effective_colors(
+        Color::to_hex,
+        foreground.to_string(),
+        background.to_string(),
+    )
+}
+
+// Pango markup for GTK labels and text views, blinking text is dropped
+pub struct PangoRenderer {
+    foreground: String,
+    background: String,
+}
+
+impl Default for PangoRenderer {
+    fn default() -> Self {
+        PangoRenderer {
+            foreground: "#000000".to_string(),
+            background: "#FFFFFF".to_string(),
+        }
+    }
+}
+
+impl PangoRenderer {
+    // the colors of the widget, used for inverted text
+    pub fn colors(mut self, foreground: &str, background: &str) -> Self {
+        self.foreground = foreground.to_string();
+        self.background = background.to_string();
+        self
+    }
+
+    pub fn render(&self, chain: &AnsiChain) -> String {
+        let mut markup = String::new();
+        for (state, text) in chain {
+            // pango parses markup as XML, where most control characters are not allowed at all
+            let text: String = text
+                .chars()
+                .filter(|&c| !c.is_control() || matches!(c, '\t' | '\n' | '\r'))
+                .collect();
+            let attributes = self.attributes_of(state);
+            if attributes.is_empty() {
+                markup.push_str(&html_escape::encode_text(&text));
+            } else {
+                markup.push_str(&format!(
+                    "<span{}>{}</span>",
+                    attributes,
+                    html_escape::encode_text(&text)
+                ));
+            }
+        }
+        markup
+    }
+
+    fn attributes_of(&self, state: &AnsiState) -> String {
+        let mut attributes = String::new();
+        let (foreground, background) = colors_of(state, &self.foreground, &self.background);
+        if let Some(foreground) = foreground {
+            attributes.push_str(&format!(
+                " foreground=\"{}\"",
+                html_escape::encode_double_quoted_attribute(&foreground)
+            ));
+        }
+        if let Some(background) = background {
+            attributes.push_str(&format!(
+                " background=\"{}\"",
+                html_escape::encode_double_quoted_attribute(&background)
+            ));
+        }
+        match state.intensity {
+            Intensity::Normal => {}
+            Intensity::Bold => attributes.push_str(" weight=\"bold\""),
+            Intensity::Faint => attributes.push_str(" weight=\"light\""),
+        }
+        if state.italic == Italics::Yes {
+            attributes.push_str(" style=\"italic\"");
+        }
+        // pango has no dotted or dashed underlines, and only draws wavy ones as `error`
+        let underline = match state.underline {
+            Underline::None => None,
+            Underline::Single | Underline::Dotted | Underline::Dashed => Some("single"),
+            Underline::Double => Some("double"),
+            Underline::Curly => Some("error"),
+        };
+        if let Some(underline) = underline {
+            attributes.push_str(&format!(" underline=\"{}\"", underline));
+            if let Some(color) = state.underline_color.to_hex() {
+                attributes.push_str(&format!(" underline_color=\"{}\"", color));
+            }
+        }
+        if state.strikethrough == StrikeThrough::Yes {
+            attributes.push_str(" strikethrough=\"true\"");
+        }
+        attributes
+    }
+}
+
+// the subset of HTML and CSS that QTextDocument understands, underline styles and colors are dropped
+pub struct QtRenderer {
+    foreground: String,
+    background: String,
+}
+
+impl Default for QtRenderer {
+    fn default() -> Self {
+        QtRenderer {
+            foreground: "#000000".to_string(),
+            background: "#FFFFFF".to_string(),
+        }
+    }
+}
+
+impl QtRenderer {
+    // the colors of the widget, used for inverted text
+    pub fn colors(mut self, foreground: &str, background: &str) -> Self {
+        self.foreground = foreground.to_string();
+        self.background = background.to_string();
+        self
+    }
+
+    pub fn render(&self, chain: &AnsiChain) -> String {
+        let mut html = String::from("<pre>");
+        for (state, text) in chain {
+            let style = self.style_of(state);
+            if style.is_empty() {
+                html.push_str(&html_escape::encode_text(text));
+            } else {
+                html.push_str(&format!(
+                    "<span style=\"{}\">{}</span>",
+                    html_escape::encode_double_quoted_attribute(&style),
+                    html_escape::encode_text(text)
+                ));
+            }
+        }
+        html.push_str("</pre>");
+        html
+    }
+
+    fn style_of(&self, state: &AnsiState) -> String {
+        let mut style = String::new();
+        let (foreground, background) = colors_of(state, &self.foreground, &self.background);
+        if let Some(foreground) = foreground {
+            style.push_str(&format!("color:{};", foreground));
+        }
+        if let Some(background) = background {
+            style.push_str(&format!("background-color:{};", background));
+        }
+        match state.intensity {
+            Intensity::Normal => {}
+            Intensity::Bold => style.push_str("font-weight:bold;"),
+            Intensity::Faint => style.push_str("font-weight:300;"),
+        }
+        if state.italic == Italics::Yes {
+            style.push_str("font-style:italic;");
+        }
+        let mut lines = Vec::new();
+        if state.underline != Underline::None {
+            lines.push("underline");
+        }
+        if state.strikethrough == StrikeThrough::Yes {
+            lines.push("line-through");
+        }
+        if !lines.is_empty() {
+            style.push_str(&format!("text-decoration:{};", lines.join(" ")));
+        }
+        style
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain() -> AnsiChain {
+        vec![
+            (AnsiState::default(), "a < b & c\x07\n".to_string()),
+            (AnsiState::from_code("[1;3;31;44m"), "error".to_string()),
+            (
+                AnsiState::from_code("[4:3;58;2;255;0;0;2m"),
+                "typo".to_string(),
+            ),
+            (AnsiState::from_code("[7;9;32m"), "\"gone\"".to_string()),
+        ]
+    }
+
+    #[test]
+    fn render_pango_markup() {
+        assert_eq!(
+            PangoRenderer::default().render(&chain()),
+            "a &lt; b &amp; c\n\
+            <span foreground=\"#CD0000\" background=\"#0000EE\" weight=\"bold\" style=\"italic\">error</span>\
+            <span weight=\"light\" underline=\"error\" underline_color=\"#FF0000\">typo</span>\
+            <span foreground=\"#FFFFFF\" background=\"#00CD00\" strikethrough=\"true\">\"gone\"</span>"
+        );
+    }
+
+    #[test]
+    fn render_qt_rich_text() {
+        assert_eq!(
+            QtRenderer::default()
+                .colors("#D0D0D0", "#1C1C1C")
+                .render(&chain()),
+            "<pre>a &lt; b &amp; c\x07\n\
+            <span style=\"color:#CD0000;background-color:#0000EE;font-weight:bold;font-style:italic;\">error</span>\
+            <span style=\"font-weight:300;text-decoration:underline;\">typo</span>\
+            <span style=\"color:#1C1C1C;background-color:#00CD00;text-decoration:line-through;\">\"gone\"</span></pre>"
+        );
+    }
+
+    #[test]
+    fn escape_widget_colors() {
+        let chain = vec![(AnsiState::from_code("[7m"), "x".to_string())];
+        assert_eq!(
+            PangoRenderer::default()
+                .colors("\"><b>", "a&b")
+                .render(&chain),
+            "<span foreground=\"a&amp;b\" background=\"&quot;&gt;&lt;b&gt;\">x</span>"
+        );
+        assert_eq!(
+            QtRenderer::default().colors("\"><b>", "a&b").render(&chain),
+            "<pre><span style=\"color:a&amp;b;background-color:&quot;&gt;&lt;b&gt;;\">x</span></pre>"
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{
+    color::Color,
+    state::{AnsiState, Intensity, Italics, StrikeThrough, Underline},
+    AnsiChain,
+};
+
+type Rgb = (u8, u8, u8);
+
+pub struct RtfRenderer {
+    font: String,
+    font_size: u32,
+}
+
+impl Default for RtfRenderer {
+    fn default() -> Self {
+        RtfRenderer {
+            font: "Courier New".to_string(),
+            font_size: 10,
+        }
+    }
+}
+
+impl RtfRenderer {
+    // the monospace font and its size in points
+    pub fn font(mut self, font: &str, font_size: u32) -> Self {
+        self.font = font.to_string();
+        self.font_size = font_size;
+        self
+    }
+
+    pub fn render(&self, chain: &AnsiChain) -> String {
+        let mut colors: Vec<Rgb> = Vec::new();
+        let mut body = String::new();
+        for (state, text) in chain {
+            let mut index_of = |rgb: Rgb| match colors.iter().position(|&c| c == rgb) {
+                Some(index) => index + 1,
+                None => {
+                    colors.push(rgb);
+                    colors.len()
+                }
+            };
+            body.push('{');
+            let (foreground, background) = colors_of(state);
+            if let Some(foreground) = foreground {
+                body.push_str(&format!("\\cf{}", index_of(foreground)));
+            }
+            if let Some(background) = background {
+                body.push_str(&format!("\\cb{}", index_of(background)));
+            }
+            if state.intensity == Intensity::Bold {
+                body.push_str("\\b");
+            }
+            if state.italic == Italics::Yes {
+                body.push_str("\\i");
+            }
+            match state.underline {
+                Underline::None => {}
+                Underline::Single => body.push_str("\\ul"),
+                Underline::Double => body.push_str("\\uldb"),
+                Underline::Curly => body.push_str("\\ulwave"),
+                Underline::Dotted => body.push_str("\\uld"),
+                Underline::Dashed => body.push_str("\\uldash"),
+            }
+            if state.underline != Underline::None {
+                if let Some(rgb) = state.underline_color.to_rgb() {
+                    body.push_str(&format!("\\ulc{}", index_of(rgb)));
+                }
+            }
+            if state.strikethrough == StrikeThrough::Yes {
+                body.push_str("\\strike");
+            }
+            // the space ends the last control word, without one it would be part of the text
+            if !body.ends_with('{') {
+                body.push(' ');
+            }
+            body.push_str(&escape(text));
+            body.push('}');
+        }
+
+        let mut rtf = format!(
+            "{{\\rtf1\\ansi\\deff0{{\\fonttbl{{\\f0\\fmodern {};}}}}",
+            escape(&self.font)
+        );
+        rtf.push_str("{\\colortbl;");
+        for (r, g, b) in colors {
+            rtf.push_str(&format!("\\red{}\\green{}\\blue{};", r, g, b));
+        }
+        rtf.push('}');
+        rtf.push_str(&format!("\\f0\\fs{} ", self.font_size * 2));
+        rtf.push_str(&body);
+        rtf.push('}');
+        rtf
+    }
+}
+
+// word processors have no default terminal colors, so inverted text falls back to black on white
+fn colors_of(state: &AnsiState) -> (Option<Rgb>, Option<Rgb>) {
+    state.effective_colors(Color::to_rgb, (0, 0, 0), (255, 255, 255))
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\line "),
+            '\t' => escaped.push_str("\\tab "),
+            c if c.is_ascii() => escaped.push(c),
+            // rtf wants signed 16 bit code units with an ascii fallback
+            c => {
+                let mut units = [0; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    escaped.push_str(&format!("\\u{}?", *unit as i16));
+                }
+            }
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_rtf_text() {
+        assert_eq!(
+            escape("a\\b{c}\n\té ─ 😀"),
+            "a\\\\b\\{c\\}\\line \\tab \\u233? \\u9472? \\u-10179?\\u-8704?"
+        );
+    }
+
+    #[test]
+    fn render_chain_with_color_table() {
+        let chain = vec![
+            (AnsiState::default(), "plain ".to_string()),
+            (AnsiState::from_code("[1;31;4:3;58;5;4m"), "red".to_string()),
+            (AnsiState::from_code("[3;9;41m"), "strike".to_string()),
+            (AnsiState::from_code("[7m"), "inverse".to_string()),
+        ];
+        assert_eq!(
+            RtfRenderer::default().font("Menlo", 12).render(&chain),
+            "{\\rtf1\\ansi\\deff0{\\fonttbl{\\f0\\fmodern Menlo;}}\
+            {\\colortbl;\\red205\\green0\\blue0;\\red0\\green0\\blue238;\\red255\\green255\\blue255;\\red0\\green0\\blue0;}\
+            \\f0\\fs24 {plain }{\\cf1\\b\\ulwave\\ulc2 red}{\\cb1\\i\\strike strike}{\\cf3\\cb4 inverse}}"
+        );
+    }
+}
@@ -0,0 +1,253 @@
+use std::fmt;
+
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{
+    color::Color,
+    state::{
+        AnsiState, Blink, Intensity, InvertColors, Italics, Script, Spacing, StrikeThrough,
+        Underline,
+    },
+};
+
+/// A run of styled text. Its JSON form stays stable between versions:
+///
+/// ```text
+/// {
+///   "text": "failed",
+///   "style": {
+///     "foreground": "red",        // null, one of the 8 palette names, a 256 color slot like 208, or "#RRGGBB"
+///     "background": null,         // same as foreground
+///     "underline_color": null,    // same as foreground
+///     "bold": true,               // bold and faint exclude each other
+///     "faint": false,
+///     "italic": false,
+///     "underline": "none",        // "none", "single", "double", "curly", "dotted" or "dashed"
+///     "blink": false,             // blink and rapid_blink exclude each other
+///     "rapid_blink": false,
+///     "inverse": false,
+///     "strikethrough": false,
+///     "proportional": false,
+///     "superscript": false,       // superscript and subscript exclude each other
+///     "subscript": false
+///   }
+/// }
+/// ```
+///
+/// When reading, missing style fields take their default value and unknown fields are ignored.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct StyledRun {
+    pub text: String,
+    pub style: AnsiState,
+}
+
+impl From<(AnsiState, String)> for StyledRun {
+    fn from((style, text): (AnsiState, String)) -> Self {
+        StyledRun { text, style }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Style {
+    foreground: Color,
+    background: Color,
+    underline_color: Color,
+    bold: bool,
+    faint: bool,
+    italic: bool,
+    underline: Underline,
+    blink: bool,
+    rapid_blink: bool,
+    inverse: bool,
+    strikethrough: bool,
+    proportional: bool,
+    superscript: bool,
+    subscript: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        AnsiState::default().into()
+    }
+}
+
+impl From<AnsiState> for Style {
+    fn from(state: AnsiState) -> Self {
+        Style {
+            foreground: state.text_color,
+            background: state.background_color,
+            underline_color: state.underline_color,
+            bold: state.intensity == Intensity::Bold,
+            faint: state.intensity == Intensity::Faint,
+            italic: state.italic == Italics::Yes,
+            underline: state.underline,
+            blink: state.blink == Blink::Slow,
+            rapid_blink: state.blink == Blink::Fast,
+            inverse: state.invert_colors == InvertColors::Yes,
+            strikethrough: state.strikethrough == StrikeThrough::Yes,
+            proportional: state.spacing == Spacing::Proportional,
+            superscript: state.script == Script::Super,
+            subscript: state.script == Script::Sub,
+        }
+    }
+}
+
+impl TryFrom<Style> for AnsiState {
+    type Error = &'static str;
+
+    fn try_from(style: Style) -> Result<Self, Self::Error> {
+        Ok(AnsiState {
+            text_color: style.foreground,
+            background_color: style.background,
+            underline_color: style.underline_color,
+            intensity: match (style.bold, style.faint) {
+                (true, true) => return Err("a style can't be both bold and faint"),
+                (true, false) => Intensity::Bold,
+                (false, true) => Intensity::Faint,
+                (false, false) => Intensity::Normal,
+            },
+            italic: if style.italic {
+                Italics::Yes
+            } else {
+                Italics::No
+            },
+            underline: style.underline,
+            blink: match (style.blink, style.rapid_blink) {
+                (true, true) => return Err("a style can't both blink and blink rapidly"),
+                (true, false) => Blink::Slow,
+                (false, true) => Blink::Fast,
+                (false, false) => Blink::None,
+            },
+            invert_colors: if style.inverse {
+                InvertColors::Yes
+            } else {
+                InvertColors::No
+            },
+            strikethrough: if style.strikethrough {
+                StrikeThrough::Yes
+            } else {
+                StrikeThrough::No
+            },
+            spacing: if style.proportional {
+                Spacing::Proportional
+            } else {
+                Spacing::Monospace
+            },
+            script: match (style.superscript, style.subscript) {
+                (true, true) => return Err("a style can't be both superscript and subscript"),
+                (true, false) => Script::Super,
+                (false, true) => Script::Sub,
+                (false, false) => Script::Normal,
+            },
+        })
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Color::None => serializer.serialize_none(),
+            Color::Byte(n) => serializer.serialize_u8(*n),
+            // `Display` already gives the palette names and `#RRGGBB`
+            _ => serializer.collect_str(self),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ColorVisitor)
+    }
+}
+
+struct ColorVisitor;
+
+impl<'de> Visitor<'de> for ColorVisitor {
+    type Value = Color;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("null, a palette name, a palette slot from 0 to 255 or a #RRGGBB color")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Color, E> {
+        Ok(Color::None)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Color, E> {
+        Ok(Color::None)
+    }
+
+    fn visit_u64<E: de::Error>(self, slot: u64) -> Result<Color, E> {
+        u8::try_from(slot)
+            .map(Color::Byte)
+            .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(slot), &self))
+    }
+
+    fn visit_i64<E: de::Error>(self, slot: i64) -> Result<Color, E> {
+        u8::try_from(slot)
+            .map(Color::Byte)
+            .map_err(|_| E::invalid_value(de::Unexpected::Signed(slot), &self))
+    }
+
+    fn visit_str<E: de::Error>(self, color: &str) -> Result<Color, E> {
+        Color::from_css(color).map_err(|_| E::invalid_value(de::Unexpected::Str(color), &self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn serialize_runs() {
+        let run = StyledRun::from((
+            AnsiState::from_code("[1;4:3;31;48;5;208;58;2;255;0;128m"),
+            "failed".to_string(),
+        ));
+        assert_eq!(
+            serde_json::to_string(&run).unwrap(),
+            "{\"text\":\"failed\",\"style\":{\"foreground\":\"red\",\"background\":208,\
+            \"underline_color\":\"#FF0080\",\"bold\":true,\"faint\":false,\"italic\":false,\
+            \"underline\":\"curly\",\"blink\":false,\"rapid_blink\":false,\"inverse\":false,\
+            \"strikethrough\":false,\"proportional\":false,\"superscript\":false,\"subscript\":false}}"
+        );
+        assert_eq!(
+            serde_json::from_str::<StyledRun>(&serde_json::to_string(&run).unwrap()).unwrap(),
+            run
+        );
+    }
+
+    #[rstest]
+    #[case("{}", Some(""))]
+    #[case("{\"foreground\":null,\"italic\":true,\"unknown\":1}", Some("[3m"))]
+    #[case(
+        "{\"foreground\":\"#010203\",\"rapid_blink\":true}",
+        Some("[38;2;1;2;3;6m")
+    )]
+    #[case(
+        "{\"background\":100,\"faint\":true,\"underline\":\"double\"}",
+        Some("[48;5;100;2;21m")
+    )]
+    #[case("{\"subscript\":true}", Some("[74m"))]
+    #[case("{\"bold\":true,\"faint\":true}", None)]
+    #[case("{\"superscript\":true,\"subscript\":true}", None)]
+    #[case("{\"foreground\":256}", None)]
+    #[case("{\"foreground\":\"chartreuse\"}", None)]
+    #[case("{\"underline\":\"zigzag\"}", None)]
+    fn deserialize_styles(#[case] json: &str, #[case] code: Option<&str>) {
+        let style = serde_json::from_str::<AnsiState>(json).ok();
+        let expected = code.map(|code| {
+            if code.is_empty() {
+                AnsiState::default()
+            } else {
+                AnsiState::from_code(code)
+            }
+        });
+        assert_eq!(style, expected);
+    }
+}
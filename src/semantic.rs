@@ -0,0 +1,130 @@
+use crate::{
+    state::{AnsiState, Intensity, Italics, Script, StrikeThrough, Underline},
+    AnsiChain,
+};
+
+// the sites whose sanitizers strip `style` attributes, each allows a slightly different set of tags
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum SemanticProfile {
+    // also GitLab, whose sanitizer allows the same tags for text
+    #[default]
+    GitHub,
+    Discourse,
+    // `<u>`, `<s>` and `<font color>`, for wikis and older forums that still allow them
+    Permissive,
+}
+
+impl SemanticProfile {
+    fn underline_tag(&self) -> &'static str {
+        match self {
+            SemanticProfile::GitHub | SemanticProfile::Discourse => "ins",
+            SemanticProfile::Permissive => "u",
+        }
+    }
+
+    fn strikethrough_tag(&self) -> &'static str {
+        match self {
+            SemanticProfile::GitHub => "del",
+            SemanticProfile::Discourse | SemanticProfile::Permissive => "s",
+        }
+    }
+
+    fn allows_font_color(&self) -> bool {
+        match self {
+            SemanticProfile::GitHub | SemanticProfile::Discourse => false,
+            SemanticProfile::Permissive => true,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SemanticRenderer {
+    profile: SemanticProfile,
+}
+
+impl SemanticRenderer {
+    pub fn profile(mut self, profile: SemanticProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    // colors are kept only where `<font>` is allowed, blinking, faint and inverted text are dropped
+    pub fn render(&self, chain: &AnsiChain) -> String {
+        let mut html = String::from("<pre>");
+        for (state, text) in chain {
+            let tags = self.tags_of(state);
+            for (tag, attributes) in &tags {
+                html.push_str(&format!("<{}{}>", tag, attributes));
+            }
+            html.push_str(&html_escape::encode_text(text));
+            for (tag, _) in tags.iter().rev() {
+                html.push_str(&format!("</{}>", tag));
+            }
+        }
+        html.push_str("</pre>");
+        html
+    }
+
+    fn tags_of(&self, state: &AnsiState) -> Vec<(&'static str, String)> {
+        let mut tags = Vec::new();
+        if self.profile.allows_font_color() {
+            if let Some(hex) = state.text_color.to_hex() {
+                tags.push(("font", format!(" color=\"{}\"", hex)));
+            }
+        }
+        if state.intensity == Intensity::Bold {
+            tags.push(("b", String::new()));
+        }
+        if state.italic == Italics::Yes {
+            tags.push(("i", String::new()));
+        }
+        if state.underline != Underline::None {
+            tags.push((self.profile.underline_tag(), String::new()));
+        }
+        if state.strikethrough == StrikeThrough::Yes {
+            tags.push((self.profile.strikethrough_tag(), String::new()));
+        }
+        match state.script {
+            Script::Normal => {}
+            Script::Super => tags.push(("sup", String::new())),
+            Script::Sub => tags.push(("sub", String::new())),
+        }
+        tags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(
+        SemanticProfile::GitHub,
+        "<pre>test &lt;a&gt; <b><i><ins><del>failed</del></ins></i></b> in 2s<sup>1</sup><sub>2</sub></pre>"
+    )]
+    #[case(
+        SemanticProfile::Discourse,
+        "<pre>test &lt;a&gt; <b><i><ins><s>failed</s></ins></i></b> in 2s<sup>1</sup><sub>2</sub></pre>"
+    )]
+    #[case(
+        SemanticProfile::Permissive,
+        "<pre>test &lt;a&gt; <font color=\"#CD0000\"><b><i><u><s>failed</s></u></i></b></font> in 2s<sup>1</sup><sub>2</sub></pre>"
+    )]
+    fn render_with_profile(#[case] profile: SemanticProfile, #[case] correct: &str) {
+        let chain = vec![
+            (AnsiState::default(), "test <a> ".to_string()),
+            (
+                AnsiState::from_code("[1;3;4;9;31;42m"),
+                "failed".to_string(),
+            ),
+            (AnsiState::from_code("[2;5;7m"), " in 2s".to_string()),
+            (AnsiState::from_code("[73m"), "1".to_string()),
+            (AnsiState::from_code("[74m"), "2".to_string()),
+        ];
+        assert_eq!(
+            SemanticRenderer::default().profile(profile).render(&chain),
+            correct
+        );
+    }
+}
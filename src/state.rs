@@ -1,20 +1,25 @@
-use std::fmt::Display;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{fmt::Display, str::FromStr};
 
 use crate::{
-    color::Color,
+    color::{Color, ColorDepth},
     error::AnsiError,
-    sub_parsers::{parse_color_code, parse_number},
+    sub_parsers::{parse_color_parameters, parse_number},
 };
 
 #[derive(Debug, PartialEq, Clone)]
-pub(crate) enum Intensity {
+pub enum Intensity {
     Normal,
     Bold,
     Faint,
 }
 
 impl Display for Intensity {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Intensity::Normal => f.write_str(""),
             Intensity::Bold => f.write_str("bold"),
@@ -24,55 +29,84 @@ impl Display for Intensity {
 }
 
 #[derive(Debug, PartialEq, Clone)]
-pub(crate) enum Blink {
+pub enum Blink {
     None,
     Fast,
     Slow,
 }
 
 #[derive(Debug, PartialEq, Clone)]
-pub(crate) enum Underline {
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Underline {
     None,
     Single,
     Double,
+    Curly,
+    Dotted,
+    Dashed,
 }
 
+// SGR 73 and 74, text raised or lowered next to the rest of the line
 #[derive(Debug, PartialEq, Clone)]
-pub(crate) enum Spacing {
+pub enum Script {
+    Normal,
+    Super,
+    Sub,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Spacing {
     Proportional,
     Monospace,
 }
 
 #[derive(Debug, PartialEq, Clone)]
-pub(crate) enum InvertColors {
+pub enum InvertColors {
     Yes,
     No,
 }
 
 #[derive(Debug, PartialEq, Clone)]
-pub(crate) enum StrikeThrough {
+pub enum StrikeThrough {
     Yes,
     No,
 }
 
 #[derive(Debug, PartialEq, Clone)]
-pub(crate) enum Italics {
+pub enum Italics {
     Yes,
     No,
 }
 
+// XTPUSHSGR and XTPOPSGR, the attributes of a push are the selective push parameters
 #[derive(Debug, PartialEq, Clone)]
+pub(crate) enum SgrStackOperation {
+    Push(Vec<u8>),
+    Pop,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "crate::schema::Style", try_from = "crate::schema::Style")
+)]
 pub struct AnsiState {
-    background_color: Color,
-    text_color: Color,
-    underline_color: Color,
-    intensity: Intensity,
-    italic: Italics,
-    underline: Underline,
-    blink: Blink,
-    invert_colors: InvertColors,
-    strikethrough: StrikeThrough,
-    spacing: Spacing,
+    pub(crate) background_color: Color,
+    pub(crate) text_color: Color,
+    pub(crate) underline_color: Color,
+    pub(crate) intensity: Intensity,
+    pub(crate) italic: Italics,
+    pub(crate) underline: Underline,
+    pub(crate) blink: Blink,
+    pub(crate) invert_colors: InvertColors,
+    pub(crate) strikethrough: StrikeThrough,
+    pub(crate) spacing: Spacing,
+    pub(crate) script: Script,
 }
 
 impl Default for AnsiState {
@@ -88,23 +122,112 @@ impl Default for AnsiState {
             intensity: Intensity::Normal,
             blink: Blink::None,
             spacing: Spacing::Monospace,
+            script: Script::Normal,
+        }
+    }
+}
+
+// the SGR parameters that set a state from scratch, like `0;1;31`, so it can be stored and resumed
+impl Display for AnsiState {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("0")?;
+        for code in self.to_sgr() {
+            write!(f, ";{}", code)?;
         }
+        Ok(())
+    }
+}
+
+impl FromStr for AnsiState {
+    type Err = AnsiError;
+
+    fn from_str(sgr: &str) -> Result<Self, Self::Err> {
+        if !sgr
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == ';' || c == ':')
+        {
+            return Err(AnsiError::InvalidFormat);
+        }
+        let mut state = AnsiState::default();
+        state.parse_ansi_code(&mut format!("[{}m", sgr).chars())?;
+        Ok(state)
     }
 }
 
 impl AnsiState {
-    pub(crate) fn to_style(&self) -> String {
+    pub fn background_color(&self) -> Color {
+        self.background_color.clone()
+    }
+
+    pub fn text_color(&self) -> Color {
+        self.text_color.clone()
+    }
+
+    pub fn underline_color(&self) -> Color {
+        self.underline_color.clone()
+    }
+
+    pub fn intensity(&self) -> Intensity {
+        self.intensity.clone()
+    }
+
+    pub fn italic(&self) -> Italics {
+        self.italic.clone()
+    }
+
+    pub fn underline(&self) -> Underline {
+        self.underline.clone()
+    }
+
+    pub fn blink(&self) -> Blink {
+        self.blink.clone()
+    }
+
+    pub fn invert_colors(&self) -> InvertColors {
+        self.invert_colors.clone()
+    }
+
+    pub fn strikethrough(&self) -> StrikeThrough {
+        self.strikethrough.clone()
+    }
+
+    pub fn spacing(&self) -> Spacing {
+        self.spacing.clone()
+    }
+
+    pub fn script(&self) -> Script {
+        self.script.clone()
+    }
+
+    pub fn to_style(&self) -> String {
         let mut s = String::new();
-        if self.background_color != Color::None {
-            s.push_str(&format!("background-color:{};", self.background_color));
-        }
-        if self.text_color != Color::None {
-            s.push_str(&format!("color:{};", self.text_color));
+        // a 256 color palette slot also goes in a custom property, which css ignores, so
+        // `from_style` can tell it apart from the full color it is shown as
+        let mut push_color = |property: &str, slot: &str, color: &Color, default: Option<&str>| {
+            match (color, default) {
+                (Color::None, None) => {}
+                (Color::None, Some(default)) => s.push_str(&format!("{}:{};", property, default)),
+                _ => s.push_str(&format!("{}:{};", property, color)),
+            }
+            if let Color::Byte(n) = color {
+                s.push_str(&format!("--ansi-{}:{};", slot, n));
+            }
+        };
+        // reverse video swaps the colors, the default ones become the page's system colors
+        if self.invert_colors == InvertColors::Yes {
+            push_color(
+                "background-color",
+                "bg",
+                &self.text_color,
+                Some("CanvasText"),
+            );
+            push_color("color", "fg", &self.background_color, Some("Canvas"));
+        } else {
+            push_color("background-color", "bg", &self.background_color, None);
+            push_color("color", "fg", &self.text_color, None);
         }
+        push_color("text-decoration-color", "ul", &self.underline_color, None);
         if self.underline != Underline::None || self.strikethrough != StrikeThrough::No {
-            if self.underline_color != Color::None {
-                s.push_str(&format!("text-decoration-color:{};", self.underline_color))
-            }
             let mut lines = Vec::new();
             if self.underline != Underline::None {
                 lines.push("underline");
@@ -114,12 +237,25 @@ impl AnsiState {
             }
             s.push_str(&format!("text-decoration:{};", lines.join(" ")));
         }
-        if self.underline == Underline::Double {
-            s.push_str("text-decoration-style: double;")
+        let decoration_style = match self.underline {
+            Underline::Double => Some("double"),
+            Underline::Curly => Some("wavy"),
+            Underline::Dotted => Some("dotted"),
+            Underline::Dashed => Some("dashed"),
+            Underline::None | Underline::Single => None,
+        };
+        if let Some(decoration_style) = decoration_style {
+            s.push_str(&format!("text-decoration-style: {};", decoration_style))
         }
         if self.intensity != Intensity::Normal {
             s.push_str(&format!("font-weight:{};", self.intensity))
         }
+        if self.italic == Italics::Yes {
+            s.push_str("font-style:italic;")
+        }
+        if self.invert_colors == InvertColors::Yes {
+            s.push_str("--ansi-inverse:1;")
+        }
         // NOTE: needs the blink animation to be defined in css
         if self.blink != Blink::None {
             let speed = match self.blink {
@@ -132,9 +268,241 @@ impl AnsiState {
         if self.spacing != Spacing::Monospace {
             s.push_str("font-family: system-ui;")
         }
+        match self.script {
+            Script::Normal => {}
+            Script::Super => s.push_str("vertical-align:super;font-size:smaller;"),
+            Script::Sub => s.push_str("vertical-align:sub;font-size:smaller;"),
+        }
         s
     }
 
+    // the inverse of `to_style`, properties that `to_style` never emits are ignored
+    #[cfg(feature = "std")]
+    pub(crate) fn from_style(style: &str) -> Result<AnsiState, AnsiError> {
+        let mut state = AnsiState::default();
+        for declaration in style.split(';').filter(|d| !d.trim().is_empty()) {
+            let (property, value) = declaration.split_once(':').ok_or(AnsiError::InvalidHtml)?;
+            let value = value.trim();
+            match property.trim() {
+                "background-color" => state.background_color = css_color(value)?,
+                "color" => state.text_color = css_color(value)?,
+                "text-decoration-color" => state.underline_color = Color::from_css(value)?,
+                "--ansi-bg" => state.background_color = palette_slot(value)?,
+                "--ansi-fg" => state.text_color = palette_slot(value)?,
+                "--ansi-ul" => state.underline_color = palette_slot(value)?,
+                "text-decoration" => {
+                    for line in value.split_whitespace() {
+                        match line {
+                            "underline" if state.underline == Underline::None => {
+                                state.underline = Underline::Single
+                            }
+                            "underline" => {}
+                            "line-through" => state.strikethrough = StrikeThrough::Yes,
+                            _ => return Err(AnsiError::InvalidHtml),
+                        }
+                    }
+                }
+                "text-decoration-style" => {
+                    state.underline = match value {
+                        "double" => Underline::Double,
+                        "wavy" => Underline::Curly,
+                        "dotted" => Underline::Dotted,
+                        "dashed" => Underline::Dashed,
+                        _ => return Err(AnsiError::InvalidHtml),
+                    }
+                }
+                "font-weight" => {
+                    state.intensity = match value {
+                        "bold" => Intensity::Bold,
+                        "light" => Intensity::Faint,
+                        _ => return Err(AnsiError::InvalidHtml),
+                    }
+                }
+                "font-style" if value == "italic" => state.italic = Italics::Yes,
+                "--ansi-inverse" if value == "1" => state.invert_colors = InvertColors::Yes,
+                "animation" => {
+                    state.blink = match value.split_whitespace().nth(1) {
+                        Some("0.5") => Blink::Fast,
+                        Some("1") => Blink::Slow,
+                        _ => return Err(AnsiError::InvalidHtml),
+                    }
+                }
+                "font-family" if value == "system-ui" => state.spacing = Spacing::Proportional,
+                "vertical-align" => {
+                    state.script = match value {
+                        "super" => Script::Super,
+                        "sub" => Script::Sub,
+                        _ => return Err(AnsiError::InvalidHtml),
+                    }
+                }
+                _ => {}
+            }
+        }
+        // the colors were written swapped
+        if state.invert_colors == InvertColors::Yes {
+            core::mem::swap(&mut state.text_color, &mut state.background_color);
+        }
+        Ok(state)
+    }
+
+    // the text and background color the text is shown in, for formats that can't invert
+    // colors themselves: reverse video swaps them and fills in the given defaults
+    #[cfg(feature = "std")]
+    pub(crate) fn effective_colors<C>(
+        &self,
+        convert: impl Fn(&Color) -> Option<C>,
+        foreground: C,
+        background: C,
+    ) -> (Option<C>, Option<C>) {
+        let (text, back) = (convert(&self.text_color), convert(&self.background_color));
+        match self.invert_colors {
+            InvertColors::No => (text, back),
+            InvertColors::Yes => (
+                Some(back.unwrap_or(background)),
+                Some(text.unwrap_or(foreground)),
+            ),
+        }
+    }
+
+    // the SGR parameters that set this state when starting from the default state
+    pub(crate) fn to_sgr(&self) -> Vec<String> {
+        let mut codes = Vec::new();
+        match self.intensity {
+            Intensity::Normal => {}
+            Intensity::Bold => codes.push("1".to_string()),
+            Intensity::Faint => codes.push("2".to_string()),
+        }
+        if self.italic == Italics::Yes {
+            codes.push("3".to_string());
+        }
+        match self.underline {
+            Underline::None => {}
+            Underline::Single => codes.push("4".to_string()),
+            Underline::Double => codes.push("21".to_string()),
+            Underline::Curly => codes.push("4:3".to_string()),
+            Underline::Dotted => codes.push("4:4".to_string()),
+            Underline::Dashed => codes.push("4:5".to_string()),
+        }
+        match self.blink {
+            Blink::None => {}
+            Blink::Slow => codes.push("5".to_string()),
+            Blink::Fast => codes.push("6".to_string()),
+        }
+        if self.invert_colors == InvertColors::Yes {
+            codes.push("7".to_string());
+        }
+        if self.strikethrough == StrikeThrough::Yes {
+            codes.push("9".to_string());
+        }
+        if self.spacing == Spacing::Proportional {
+            codes.push("26".to_string());
+        }
+        match self.script {
+            Script::Normal => {}
+            Script::Super => codes.push("73".to_string()),
+            Script::Sub => codes.push("74".to_string()),
+        }
+        if self.text_color != Color::None {
+            codes.push(self.text_color.to_sgr(30));
+        }
+        if self.background_color != Color::None {
+            codes.push(self.background_color.to_sgr(40));
+        }
+        if self.underline_color != Color::None {
+            codes.push(self.underline_color.to_sgr(50));
+        }
+        codes
+    }
+
+    // the SGR parameters that change this state into `next` without resetting
+    pub(crate) fn transition_sgr(&self, next: &AnsiState) -> Vec<String> {
+        let mut codes = Vec::new();
+        if self.intensity != next.intensity {
+            match next.intensity {
+                Intensity::Normal => codes.push("22".to_string()),
+                // most terminals show bold and faint at the same time
+                Intensity::Bold if self.intensity == Intensity::Faint => {
+                    codes.push("22;1".to_string())
+                }
+                Intensity::Faint if self.intensity == Intensity::Bold => {
+                    codes.push("22;2".to_string())
+                }
+                Intensity::Bold => codes.push("1".to_string()),
+                Intensity::Faint => codes.push("2".to_string()),
+            }
+        }
+        if self.italic != next.italic {
+            match next.italic {
+                Italics::Yes => codes.push("3".to_string()),
+                Italics::No => codes.push("23".to_string()),
+            }
+        }
+        if self.underline != next.underline {
+            match next.underline {
+                Underline::None => codes.push("24".to_string()),
+                Underline::Single => codes.push("4".to_string()),
+                Underline::Double => codes.push("21".to_string()),
+                Underline::Curly => codes.push("4:3".to_string()),
+                Underline::Dotted => codes.push("4:4".to_string()),
+                Underline::Dashed => codes.push("4:5".to_string()),
+            }
+        }
+        if self.blink != next.blink {
+            match next.blink {
+                Blink::None => codes.push("25".to_string()),
+                Blink::Slow => codes.push("5".to_string()),
+                Blink::Fast => codes.push("6".to_string()),
+            }
+        }
+        if self.invert_colors != next.invert_colors {
+            match next.invert_colors {
+                InvertColors::Yes => codes.push("7".to_string()),
+                InvertColors::No => codes.push("27".to_string()),
+            }
+        }
+        if self.strikethrough != next.strikethrough {
+            match next.strikethrough {
+                StrikeThrough::Yes => codes.push("9".to_string()),
+                StrikeThrough::No => codes.push("29".to_string()),
+            }
+        }
+        if self.spacing != next.spacing {
+            match next.spacing {
+                Spacing::Proportional => codes.push("26".to_string()),
+                Spacing::Monospace => codes.push("50".to_string()),
+            }
+        }
+        if self.script != next.script {
+            match next.script {
+                Script::Normal => codes.push("75".to_string()),
+                Script::Super => codes.push("73".to_string()),
+                Script::Sub => codes.push("74".to_string()),
+            }
+        }
+        if self.text_color != next.text_color {
+            codes.push(next.text_color.to_sgr(30));
+        }
+        if self.background_color != next.background_color {
+            codes.push(next.background_color.to_sgr(40));
+        }
+        if self.underline_color != next.underline_color {
+            codes.push(next.underline_color.to_sgr(50));
+        }
+        codes
+    }
+
+    pub(crate) fn quantized(&self, depth: ColorDepth) -> AnsiState {
+        let mut state = self.clone();
+        state.text_color = self.text_color.quantize(depth);
+        state.background_color = self.background_color.quantize(depth);
+        // terminals without 256 colors don't know about underline colors either
+        state.underline_color = match depth {
+            ColorDepth::TrueColor | ColorDepth::Palette256 => self.underline_color.quantize(depth),
+            ColorDepth::Palette16 | ColorDepth::None => Color::None,
+        };
+        state
+    }
+
     #[allow(unused)]
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
@@ -160,6 +528,51 @@ impl AnsiState {
             intensity,
             blink,
             spacing,
+            script: Script::Normal,
+        }
+    }
+
+    // the state after a single escape code like "[1;31m", for tests
+    #[cfg(test)]
+    pub(crate) fn from_code(code: &str) -> AnsiState {
+        let mut state = AnsiState::default();
+        state.parse_ansi_code(&mut code.chars()).unwrap();
+        state
+    }
+
+    // the style of a man page style overstruck character
+    pub(crate) fn overstruck(&self, bold: bool, underline: bool) -> AnsiState {
+        let mut state = self.clone();
+        if bold {
+            state.intensity = Intensity::Bold;
+        }
+        if underline {
+            state.underline = Underline::Single;
+        }
+        state
+    }
+
+    // restores the attributes saved by a (selective) push, an empty selection restores everything
+    pub(crate) fn restore(&mut self, saved: AnsiState, attributes: &[u8]) {
+        if attributes.is_empty() {
+            *self = saved;
+            return;
+        }
+        for attribute in attributes {
+            match attribute {
+                1 | 2 => self.intensity = saved.intensity.clone(),
+                3 => self.italic = saved.italic.clone(),
+                4 | 21 => {
+                    self.underline = saved.underline.clone();
+                    self.underline_color = saved.underline_color.clone();
+                }
+                5 => self.blink = saved.blink.clone(),
+                7 => self.invert_colors = saved.invert_colors.clone(),
+                9 => self.strikethrough = saved.strikethrough.clone(),
+                30 => self.text_color = saved.text_color.clone(),
+                31 => self.background_color = saved.background_color.clone(),
+                _ => {}
+            }
         }
     }
 
@@ -170,34 +583,76 @@ impl AnsiState {
         if characters.next() != Some('[') {
             return Err(AnsiError::InvalidStartBrace);
         }
-        match parse_number(
-            &mut characters
-                .take_while(|&c| c != 'm' && c != ';')
-                .map(|c| c as u8),
-        )
-        .0?
-        {
-            0 => {
-                self.background_color = Color::None;
-                self.text_color = Color::None;
-                self.intensity = Intensity::Normal;
-                self.italic = Italics::No;
-                self.underline = Underline::None;
-                self.blink = Blink::None;
-                self.invert_colors = InvertColors::No;
-                self.strikethrough = StrikeThrough::No;
+        let mut terminator = None;
+        let parameters: String = characters
+            .take_while(|&c| {
+                terminator = Some(c);
+                !('\x40'..='\x7e').contains(&c)
+            })
+            .collect();
+        match terminator {
+            Some(c) if ('\x40'..='\x7e').contains(&c) => {
+                self.apply_control_sequence(&parameters, c)
             }
+            // the input ended before the final byte
+            _ => Err(AnsiError::InvalidFormat),
+        }
+    }
+
+    // applies the control sequence with these parameter bytes and final byte
+    pub(crate) fn apply_control_sequence(
+        &mut self,
+        parameters: &str,
+        final_byte: char,
+    ) -> Result<(), AnsiError> {
+        match final_byte {
+            // private modes like `[>4;2m` can end in `m` as well
+            'm' if !parameters.starts_with(['<', '=', '>', '?']) => {}
+            // erasing, moving the cursor and the other control functions don't change the style
+            _ => return Ok(()),
+        }
+        let mut parameters = parameters.split(';');
+        while let Some(parameter) = parameters.next() {
+            self.apply_sgr_parameter(parameter, &mut parameters)?;
+        }
+        Ok(())
+    }
+
+    fn apply_sgr_parameter<'a>(
+        &mut self,
+        parameter: &str,
+        parameters: &mut impl Iterator<Item = &'a str>,
+    ) -> Result<(), AnsiError> {
+        let (code, sub_parameters) = match parameter.split_once(':') {
+            Some((code, sub_parameters)) => (code, Some(sub_parameters)),
+            None => (parameter, None),
+        };
+        let code = parse_number(&mut code.bytes()).0?;
+        // only a few codes have colon separated sub parameters
+        if sub_parameters.is_some() && !matches!(code, 4 | 38 | 48 | 58) {
+            return Err(AnsiError::InvalidFormat);
+        }
+        match code {
+            0 => *self = AnsiState::default(),
             1 => self.intensity = Intensity::Bold,
             2 => self.intensity = Intensity::Faint,
             3 => self.italic = Italics::Yes,
+            4 if sub_parameters.is_some() => {
+                self.underline = match parse_number(&mut sub_parameters.unwrap_or("").bytes()).0? {
+                    0 => Underline::None,
+                    1 => Underline::Single,
+                    2 => Underline::Double,
+                    3 => Underline::Curly,
+                    4 => Underline::Dotted,
+                    5 => Underline::Dashed,
+                    _ => return Err(AnsiError::IllegalCommand),
+                }
+            }
             4 => self.underline = Underline::Single,
             5 => self.blink = Blink::Slow,
             6 => self.blink = Blink::Fast,
             7 => self.invert_colors = InvertColors::Yes,
-            8 => todo!("Conceal or hide"),
             9 => self.strikethrough = StrikeThrough::Yes,
-            10..20 => todo!("fonts"),
-            20 => todo!("Fraktur???"),
             21 => self.underline = Underline::Double,
             22 => self.intensity = Intensity::Normal,
             23 => self.italic = Italics::No,
@@ -205,7 +660,6 @@ impl AnsiState {
             25 => self.blink = Blink::None,
             26 => self.spacing = Spacing::Proportional,
             27 => self.invert_colors = InvertColors::No,
-            28 => todo!("reveal (undo 8)"),
             29 => self.strikethrough = StrikeThrough::No,
             30 => self.text_color = Color::Black,
             31 => self.text_color = Color::Red,
@@ -215,7 +669,7 @@ impl AnsiState {
             35 => self.text_color = Color::Magenta,
             36 => self.text_color = Color::Cyan,
             37 => self.text_color = Color::White,
-            38 => self.text_color = parse_color_code(characters)?,
+            38 => self.text_color = parse_color_parameters(sub_parameters, parameters)?,
             39 => self.text_color = Color::None,
             40 => self.background_color = Color::Black,
             41 => self.background_color = Color::Red,
@@ -225,35 +679,285 @@ impl AnsiState {
             45 => self.background_color = Color::Magenta,
             46 => self.background_color = Color::Cyan,
             47 => self.background_color = Color::White,
-            48 => self.background_color = parse_color_code(characters)?,
+            48 => self.background_color = parse_color_parameters(sub_parameters, parameters)?,
             49 => self.background_color = Color::None,
             50 => self.spacing = Spacing::Monospace,
-            51 => todo!("framed?"),
-            52 => todo!("encircled?"),
-            53 => todo!("overlined"),
-            54 => todo!("neither framed or encircled"),
-            55 => todo!("not overlined"),
-            58 => self.underline_color = parse_color_code(characters)?,
+            58 => self.underline_color = parse_color_parameters(sub_parameters, parameters)?,
             59 => self.underline_color = Color::None,
-            60 => todo!("Ideogram underline or right side line"),
-            61 => todo!("Ideogram double underline, or double line on the right side"),
-            62 => todo!("Ideogram overline or left side line"),
-            63 => todo!("Ideogram double overline, or double line on the left side"),
-            64 => todo!("Ideogram stress marking"),
-            65 => todo!("No ideogram attributes"),
-            73 => todo!("superscript"),
-            74 => todo!("subscript"),
-            75 => todo!("neither super nor subscript"),
+            73 => self.script = Script::Super,
+            74 => self.script = Script::Sub,
+            75 => self.script = Script::Normal,
+            // conceal and reveal, fonts, Fraktur, frames, overlines and ideogram lines have no
+            // equivalent yet and are ignored
+            8 | 10..=20 | 28 | 51..=55 | 60..=65 => {}
+            // the bright colors are the palette entries 8 to 15
+            90..=97 => self.text_color = Color::Byte(code - 82),
+            100..=107 => self.background_color = Color::Byte(code - 92),
             _ => return Err(AnsiError::IllegalCommand),
         };
         Ok(())
     }
 }
 
+// the system colors `to_style` uses for the default colors of reverse video
+#[cfg(feature = "std")]
+fn css_color(value: &str) -> Result<Color, AnsiError> {
+    match value {
+        "Canvas" | "CanvasText" => Ok(Color::None),
+        _ => Color::from_css(value),
+    }
+}
+
+#[cfg(feature = "std")]
+fn palette_slot(value: &str) -> Result<Color, AnsiError> {
+    value
+        .parse()
+        .map(Color::Byte)
+        .map_err(|_| AnsiError::InvalidHtml)
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("[4:0m", Underline::None, "")]
+    #[case("[4:1m", Underline::Single, "text-decoration:underline;")]
+    #[case(
+        "[4:2m",
+        Underline::Double,
+        "text-decoration:underline;text-decoration-style: double;"
+    )]
+    #[case(
+        "[4:3m",
+        Underline::Curly,
+        "text-decoration:underline;text-decoration-style: wavy;"
+    )]
+    #[case(
+        "[4:4m",
+        Underline::Dotted,
+        "text-decoration:underline;text-decoration-style: dotted;"
+    )]
+    #[case(
+        "[4:5m",
+        Underline::Dashed,
+        "text-decoration:underline;text-decoration-style: dashed;"
+    )]
+    fn parse_underline_styles(
+        #[case] code: &str,
+        #[case] underline: Underline,
+        #[case] style: &str,
+    ) {
+        let mut state = AnsiState::default();
+        assert_eq!(state.parse_ansi_code(&mut code.chars()), Ok(()));
+        assert_eq!(state.underline, underline);
+        assert_eq!(state.to_style(), style);
+    }
+
+    #[rstest]
+    #[case("[8m")]
+    #[case("[12m")]
+    #[case("[20m")]
+    #[case("[28m")]
+    #[case("[53m")]
+    #[case("[55m")]
+    #[case("[62m")]
+    #[case("[65m")]
+    fn ignore_unsupported_attributes(#[case] code: &str) {
+        let mut state = AnsiState::default();
+        assert_eq!(state.parse_ansi_code(&mut code.chars()), Ok(()));
+        assert_eq!(state, AnsiState::default());
+    }
+
+    #[rstest]
+    #[case("[4:6m", AnsiError::IllegalCommand)]
+    #[case("[1:3m", AnsiError::InvalidFormat)]
+    #[case("[38:9:1m", AnsiError::InvalidFormat)]
+    fn parse_invalid_sub_parameters(#[case] code: &str, #[case] error: AnsiError) {
+        let mut state = AnsiState::default();
+        assert_eq!(state.parse_ansi_code(&mut code.chars()), Err(error));
+    }
+
+    #[rstest]
+    #[case("[3m", "font-style:italic;")]
+    #[case("[7m", "background-color:CanvasText;color:Canvas;--ansi-inverse:1;")]
+    #[case(
+        "[7;31;48;5;208m",
+        "background-color:red;color:#FF8700;--ansi-fg:208;--ansi-inverse:1;"
+    )]
+    #[case("[58;5;1m", "text-decoration-color:red;")]
+    #[case("[73m", "vertical-align:super;font-size:smaller;")]
+    #[case("[74m", "vertical-align:sub;font-size:smaller;")]
+    #[case("[73;75m", "")]
+    fn style_of_single_attributes(#[case] code: &str, #[case] style: &str) {
+        let mut state = AnsiState::default();
+        assert_eq!(state.parse_ansi_code(&mut code.chars()), Ok(()));
+        assert_eq!(state.to_style(), style);
+    }
+
+    #[cfg(feature = "std")]
+    #[rstest]
+    #[case("[1m")]
+    #[case("[2m")]
+    #[case("[3m")]
+    #[case("[4m")]
+    #[case("[4:3m")]
+    #[case("[4:4m")]
+    #[case("[4:5m")]
+    #[case("[5m")]
+    #[case("[6m")]
+    #[case("[7m")]
+    #[case("[7;31m")]
+    #[case("[7;38;5;1;48;2;1;2;3m")]
+    #[case("[9m")]
+    #[case("[21m")]
+    #[case("[26m")]
+    #[case("[33m")]
+    #[case("[38;2;1;2;3m")]
+    #[case("[43m")]
+    #[case("[48;2;4;5;6m")]
+    #[case("[58;2;7;8;9m")]
+    #[case("[58;5;3m")]
+    #[case("[38;5;208;48;5;16;58;5;231m")]
+    #[case("[91m")]
+    #[case("[73m")]
+    #[case("[74m")]
+    fn style_and_sgr_round_trip(#[case] code: &str) {
+        let mut state = AnsiState::default();
+        assert_eq!(state.parse_ansi_code(&mut code.chars()), Ok(()));
+        assert_eq!(AnsiState::from_style(&state.to_style()), Ok(state.clone()));
+
+        let mut reparsed = AnsiState::default();
+        for code in state.to_sgr() {
+            let sequence = format!("[{}m", code);
+            assert_eq!(reparsed.parse_ansi_code(&mut sequence.chars()), Ok(()));
+        }
+        assert_eq!(reparsed, state);
+    }
+
+    #[cfg(feature = "std")]
+    #[rstest]
+    #[case("font-weight:heavy;")]
+    #[case("color:#12345;")]
+    #[case("text-decoration:overline;")]
+    #[case("--ansi-fg:256;")]
+    #[case("no declaration")]
+    fn invalid_styles(#[case] style: &str) {
+        assert_eq!(AnsiState::from_style(style), Err(AnsiError::InvalidHtml));
+    }
+
+    #[test]
+    fn parse_multiple_parameters() {
+        let mut state = AnsiState::default();
+        assert_eq!(
+            state.parse_ansi_code(&mut "[1;38;5;208;4:3;48;2;1;2;3;92;3m".chars()),
+            Ok(())
+        );
+        assert_eq!(state.intensity, Intensity::Bold);
+        assert_eq!(state.text_color, Color::Byte(10));
+        assert_eq!(state.underline, Underline::Curly);
+        assert_eq!(state.background_color, Color::Full(1, 2, 3));
+        assert_eq!(state.italic, Italics::Yes);
+
+        assert_eq!(state.parse_ansi_code(&mut "[;103m".chars()), Ok(()));
+        assert_eq!(
+            state,
+            AnsiState {
+                background_color: Color::Byte(11),
+                ..Default::default()
+            }
+        );
+
+        assert_eq!(state.parse_ansi_code(&mut "[m".chars()), Ok(()));
+        assert_eq!(state, AnsiState::default());
+        assert_eq!(
+            state.parse_ansi_code(&mut "[1;2".chars()),
+            Err(AnsiError::InvalidFormat)
+        );
+    }
+
+    #[rstest]
+    #[case("[2J")]
+    #[case("[K")]
+    #[case("[10;20H")]
+    #[case("[?25l")]
+    #[case("[>4;2m")]
+    #[case("[0 q")]
+    fn skip_other_control_sequences(#[case] code: &str) {
+        let mut state = AnsiState::from_code("[1m");
+        assert_eq!(state.parse_ansi_code(&mut code.chars()), Ok(()));
+        assert_eq!(state, AnsiState::from_code("[1m"));
+    }
+
+    #[test]
+    fn reset_clears_every_attribute() {
+        // ECMA-48 has SGR 0 cancel the effect of every preceding SGR, underline colors and
+        // proportional spacing included
+        let mut state = AnsiState::default();
+        assert_eq!(
+            state.parse_ansi_code(&mut "[1;3;4;7;26;31;48;5;208;58;5;1;73m".chars()),
+            Ok(())
+        );
+        assert_eq!(state.parse_ansi_code(&mut "[0m".chars()), Ok(()));
+        assert_eq!(state, AnsiState::default());
+    }
+
+    #[rstest]
+    #[case("[1m", "[2m", "22;2")]
+    #[case("[1;3;4;31m", "[1;3;4:3;31m", "4:3")]
+    #[case("[5;7;9;26m", "[m", "25;27;29;50")]
+    #[case("[38;2;1;2;3m", "[48;5;9;58;5;1m", "39;101;58;5;1")]
+    #[case("[73m", "[74m", "74")]
+    #[case("[74m", "[m", "75")]
+    fn transitions_between_states(#[case] from: &str, #[case] to: &str, #[case] correct: &str) {
+        let mut from_state = AnsiState::default();
+        from_state.parse_ansi_code(&mut from.chars()).unwrap();
+        let mut to_state = AnsiState::default();
+        to_state.parse_ansi_code(&mut to.chars()).unwrap();
+        assert_eq!(from_state.transition_sgr(&to_state).join(";"), correct);
+
+        let sequence = format!("[{}m", correct);
+        from_state.parse_ansi_code(&mut sequence.chars()).unwrap();
+        assert_eq!(from_state, to_state);
+    }
+
+    #[test]
+    fn restore_selected_attributes() {
+        let mut saved = AnsiState::default();
+        saved.parse_ansi_code(&mut "[1m".chars()).unwrap();
+        saved.parse_ansi_code(&mut "[31m".chars()).unwrap();
+        saved.parse_ansi_code(&mut "[42m".chars()).unwrap();
+
+        let mut state = AnsiState::default();
+        state.parse_ansi_code(&mut "[3m".chars()).unwrap();
+        state.restore(saved.clone(), &[1, 30]);
+        assert_eq!(state.intensity, Intensity::Bold);
+        assert_eq!(state.text_color, Color::Red);
+        assert_eq!(state.background_color, Color::None);
+        assert_eq!(state.italic, Italics::Yes);
+
+        state.restore(saved.clone(), &[]);
+        assert_eq!(state, saved);
+    }
+
+    #[test]
+    fn parse_colon_colors() {
+        let mut state = AnsiState::default();
+        assert_eq!(state.parse_ansi_code(&mut "[4:3m".chars()), Ok(()));
+        assert_eq!(
+            state.parse_ansi_code(&mut "[58:2::255:0:0m".chars()),
+            Ok(())
+        );
+        assert_eq!(state.parse_ansi_code(&mut "[38:5:208m".chars()), Ok(()));
+        assert_eq!(state.parse_ansi_code(&mut "[48:2:0:0:64m".chars()), Ok(()));
+        assert_eq!(
+            state.to_style(),
+            "background-color:#000040;color:#FF8700;--ansi-fg:208;text-decoration-color:#FF0000;\
+            text-decoration:underline;text-decoration-style: wavy;"
+        );
+    }
 
     #[test]
     fn parse_ansi_codes() {
@@ -0,0 +1,139 @@
+use crate::{error::AnsiError, Formatter, Parser};
+
+// converts utf-8 input arriving in pieces to html as it comes in, for input too big or too slow to collect first.
+// runs continuing over a piece border become two spans, which look the same but aren't byte-identical to `convert`
+pub struct HtmlConverter {
+    parser: Parser,
+    // the start of a utf-8 sequence cut off at the end of the last piece
+    carry: Vec<u8>,
+    started: bool,
+}
+
+impl HtmlConverter {
+    pub fn new(parser: Parser) -> HtmlConverter {
+        HtmlConverter {
+            parser,
+            carry: Vec::new(),
+            started: false,
+        }
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<String, AnsiError> {
+        self.carry.extend_from_slice(bytes);
+        let complete = complete_utf8_len(&self.carry);
+        let text = self.parser.decode(&self.carry[..complete]).into_owned();
+        self.carry.drain(..complete);
+        self.convert(&text)
+    }
+
+    pub fn finish(mut self) -> Result<String, AnsiError> {
+        let text = self.parser.decode(&self.carry).into_owned();
+        let mut html = self.convert(&text)?;
+        if !self.started {
+            html.push_str("<pre>");
+        }
+        html.push_str("</pre>");
+        Ok(html)
+    }
+
+    fn convert(&mut self, text: &str) -> Result<String, AnsiError> {
+        let mut html = String::new();
+        if !self.started {
+            html.push_str("<pre>");
+            self.started = true;
+        }
+        for (state, text) in self.parser.parse_str(text)? {
+            Formatter::write_run(&state, &text, &mut html).expect("writing to a String can't fail");
+        }
+        Ok(html)
+    }
+}
+
+// the length of `bytes` without a utf-8 sequence that is cut off at the end
+fn complete_utf8_len(bytes: &[u8]) -> usize {
+    for back in 1..=bytes.len().min(3) {
+        let index = bytes.len() - back;
+        let needed = match bytes[index] {
+            // a continuation byte, the sequence starts further back
+            0x80..=0xbf => continue,
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => 1,
+        };
+        return if back < needed { index } else { bytes.len() };
+    }
+    bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::merged;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(b"", 0)]
+    #[case(b"abc", 3)]
+    #[case(b"a\xc3", 1)]
+    #[case(b"a\xc3\xa9", 3)]
+    #[case(b"\xe2\x94", 0)]
+    #[case(b"\xf0\x9f\x98", 0)]
+    #[case(b"\xf0\x9f\x98\x80", 4)]
+    #[case(b"\x80\x80\x80\x80", 4)]
+    fn find_cut_off_utf8(#[case] bytes: &[u8], #[case] complete: usize) {
+        assert_eq!(complete_utf8_len(bytes), complete);
+    }
+
+    #[test]
+    fn convert_in_pieces() {
+        let text = "plain \x1b[1mbold \u{e9}\u{1f600}\x1b[0m \x1b]0;title\x07<end>".as_bytes();
+        let whole = crate::convert(&mut std::str::from_utf8(text).unwrap().chars()).unwrap();
+        for size in 1..text.len() {
+            let mut converter = HtmlConverter::new(Parser::default());
+            let mut html = String::new();
+            for piece in text.chunks(size) {
+                html.push_str(&converter.feed(piece).unwrap());
+            }
+            html.push_str(&converter.finish().unwrap());
+            assert_eq!(merged(html), whole, "pieces of {}", size);
+        }
+        assert_eq!(
+            HtmlConverter::new(Parser::default()).finish(),
+            Ok("<pre></pre>".to_string())
+        );
+        assert_eq!(
+            HtmlConverter::new(Parser::default()).feed(b"\x1b[1:3mx"),
+            Err(AnsiError::InvalidFormat)
+        );
+    }
+    #[test]
+    fn convert_raw_c1_bytes() {
+        let mut converter = HtmlConverter::new(Parser::default().c1_controls(true));
+        let mut html = converter.feed(b"\x9b3").unwrap();
+        html.push_str(&converter.feed(b"1mred\x9d0;title\x9c\x9b").unwrap());
+        html.push_str(&converter.feed(b"0m plain\xc3").unwrap());
+        html.push_str(&converter.feed(b"\x9b").unwrap());
+        html.push_str(&converter.finish().unwrap());
+        assert_eq!(
+            html,
+            "<pre><span style=\"color:red;\">red</span> plain\u{db}</pre>"
+        );
+    }
+
+    #[test]
+    fn unterminated_control_string_is_given_up_on() {
+        let mut converter = HtmlConverter::new(Parser::default());
+        let mut html = converter.feed(b"start \x1b]0;x").unwrap();
+        for line in 0..1000 {
+            html.push_str(
+                &converter
+                    .feed(format!("line {}\n", line).as_bytes())
+                    .unwrap(),
+            );
+        }
+        assert!(converter.parser.pending.len() <= crate::PENDING_LIMIT);
+        assert!(html.starts_with("<pre>start ]0;xline 0\n"));
+        assert!(html.ends_with("line 999\n"));
+    }
+}
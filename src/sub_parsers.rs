@@ -1,4 +1,12 @@
-use crate::{color::Color, error::AnsiError};
+use alloc::{string::ToString, vec::Vec};
+
+use crate::{
+    charset::Charset,
+    color::Color,
+    error::AnsiError,
+    state::{AnsiState, SgrStackOperation},
+    AnsiChain,
+};
 
 pub(crate) fn parse_number(part: &mut impl Iterator<Item = u8>) -> (Result<u8, AnsiError>, u32) {
     part.fold((Ok(0), 0), |(total, length), char| match total {
@@ -16,55 +24,247 @@ pub(crate) fn parse_number(part: &mut impl Iterator<Item = u8>) -> (Result<u8, A
     })
 }
 
-pub(crate) fn parse_color_code(part: &mut impl Iterator<Item = char>) -> Result<Color, AnsiError> {
-    // match `(2|5);`
-    let selector = part.next();
-    if part.next() != Some(';') {
-        return Err(AnsiError::InvalidFormat);
+// the arguments of a 38, 48 or 58 in one string, like `5;208`, `2;1;2;3` or `2::1:2:3`
+pub(crate) fn parse_color_code(code: &str) -> Result<Color, AnsiError> {
+    // match `(2|3|4|5)(;|:)`, the colon separated forms are from ISO 8613-6
+    let arguments = code.get(2..).unwrap_or("");
+    match (code.get(..1), code.get(1..2)) {
+        (Some("5"), Some(";" | ":")) => palette_color(arguments),
+        (Some("2"), Some(";")) => {
+            let mut splits = arguments.split(';');
+            match [splits.next(), splits.next(), splits.next(), splits.next()] {
+                [Some(r), Some(g), Some(b), None] => full_color([r, g, b]),
+                _ => Err(AnsiError::InvalidFormat),
+            }
+        }
+        (Some("2"), Some(":")) => {
+            let rgb = parse_sub_parameters(arguments, 3)?;
+            Ok(Color::Full(rgb[0], rgb[1], rgb[2]))
+        }
+        (Some("3"), Some(":")) => {
+            let cmy = parse_sub_parameters(arguments, 3)?;
+            Ok(Color::Full(255 - cmy[0], 255 - cmy[1], 255 - cmy[2]))
+        }
+        (Some("4"), Some(":")) => {
+            let cmyk = parse_sub_parameters(arguments, 4)?;
+            let key = |c: u8| ((255 - c) as u16 * (255 - cmyk[3]) as u16 / 255) as u8;
+            Ok(Color::Full(key(cmyk[0]), key(cmyk[1]), key(cmyk[2])))
+        }
+        _ => Err(AnsiError::InvalidFormat),
+    }
+}
+
+fn palette_color(slot: &str) -> Result<Color, AnsiError> {
+    match parse_component(slot)? {
+        0 => Ok(Color::Black),
+        1 => Ok(Color::Red),
+        2 => Ok(Color::Green),
+        3 => Ok(Color::Yellow),
+        4 => Ok(Color::Blue),
+        5 => Ok(Color::Magenta),
+        6 => Ok(Color::Cyan),
+        7 => Ok(Color::White),
+        n => Ok(Color::Byte(n)),
+    }
+}
+
+fn full_color([r, g, b]: [&str; 3]) -> Result<Color, AnsiError> {
+    Ok(Color::Full(
+        parse_component(r)?,
+        parse_component(g)?,
+        parse_component(b)?,
+    ))
+}
+
+// match `[{n};{n}...#(\{|p)` and `[#(\}|q)`
+pub(crate) fn parse_sgr_stack_operation(
+    part: &mut impl Iterator<Item = char>,
+) -> Option<SgrStackOperation> {
+    if part.next() != Some('[') {
+        return None;
     }
-    match selector {
-        Some('5') => {
-            let (n, length) = parse_number(&mut part.take_while(|&p| p != 'm').map(|p| p as u8));
-            if length > 3 {
-                return Err(AnsiError::TooLong);
-            };
-            match n {
-                Ok(0) => Ok(Color::Black),
-                Ok(1) => Ok(Color::Red),
-                Ok(2) => Ok(Color::Green),
-                Ok(3) => Ok(Color::Yellow),
-                Ok(4) => Ok(Color::Blue),
-                Ok(5) => Ok(Color::Magenta),
-                Ok(6) => Ok(Color::Cyan),
-                Ok(7) => Ok(Color::White),
-                Ok(n) => Ok(Color::Byte(n)),
-                Err(err) => Err(err),
+    // every escape code is tried as a stack operation first, so this parses as it goes
+    let mut attributes = Vec::new();
+    let (mut attribute, mut empty) = (None::<u16>, true);
+    loop {
+        let c = part.next()?;
+        if let Some(digit) = c.to_digit(10) {
+            attribute = Some(
+                attribute
+                    .unwrap_or(0)
+                    .saturating_mul(10)
+                    .saturating_add(digit as u16),
+            );
+        } else if c == ';' || c == '#' {
+            // unknown or invalid attributes are ignored, just like xterm does
+            attributes.extend(attribute.take().and_then(|n| u8::try_from(n).ok()));
+            if c == '#' {
+                break;
             }
+        } else {
+            return None;
         }
-        Some('2') => {
-            let color: Vec<char> = part.take_while(|&p| p != 'm').take(11).collect();
+        empty = false;
+    }
+    match part.next()? {
+        '{' | 'p' => Some(SgrStackOperation::Push(attributes)),
+        '}' | 'q' if empty => Some(SgrStackOperation::Pop),
+        _ => None,
+    }
+}
 
-            let splits: Vec<_> = color.split(|&byte| byte == ';').collect();
-            if splits.len() != 3 {
-                return Err(AnsiError::InvalidFormat);
+// match `(\(|\))(0|B)`, designating G0 or G1, other character sets are treated as ascii
+pub(crate) fn parse_charset_designation(
+    part: &mut impl Iterator<Item = char>,
+) -> Option<(usize, Charset)> {
+    let slot = match part.next()? {
+        '(' => 0,
+        ')' => 1,
+        _ => return None,
+    };
+    match part.next()? {
+        '0' => Some((slot, Charset::DecSpecialGraphics)),
+        _ => Some((slot, Charset::Ascii)),
+    }
+}
+
+// skips an OSC, DCS, SOS, PM or APC string up to and including its BEL or ST terminator
+pub(crate) fn skip_control_string(part: &mut impl Iterator<Item = char>) -> Result<(), AnsiError> {
+    match part.next() {
+        // a lone string terminator
+        Some('\\') => return Ok(()),
+        Some(']' | 'P' | 'X' | '^' | '_') => {}
+        _ => return Err(AnsiError::InvalidStartBrace),
+    }
+    let mut escaped = false;
+    for c in part {
+        match c {
+            '\x07' => return Ok(()),
+            '\\' if escaped => return Ok(()),
+            _ => escaped = c == '\x1b',
+        }
+    }
+    Err(AnsiError::InvalidFormat)
+}
+
+// rewrites the 8-bit C1 controls that start or end escape codes (DCS, SOS, CSI, ST, OSC, PM and
+// APC) to their 7-bit `ESC Fe` equivalents, so 0x9B becomes `ESC [`. the others, like NEL, are text
+#[derive(Clone)]
+pub(crate) struct C1Controls<T> {
+    characters: T,
+    pending: Option<char>,
+}
+
+impl<T> C1Controls<T> {
+    pub(crate) fn new(characters: T) -> Self {
+        C1Controls {
+            characters,
+            pending: None,
+        }
+    }
+
+    pub(crate) fn into_inner(self) -> T {
+        self.characters
+    }
+}
+
+impl<T: Iterator<Item = char>> Iterator for C1Controls<T> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if let Some(c) = self.pending.take() {
+            return Some(c);
+        }
+        match self.characters.next()? {
+            c @ ('\u{90}' | '\u{98}' | '\u{9b}'..='\u{9f}') => {
+                self.pending = Some((c as u8 - 0x40) as char);
+                Some('\x1b')
             }
+            c => Some(c),
+        }
+    }
+}
 
-            let cparts: Vec<Result<u8, AnsiError>> = splits
-                .into_iter()
-                .map(|split| {
-                    let (total, length) = parse_number(&mut split.iter().map(|&p| p as u8));
-                    if length > 3 {
-                        return Err(AnsiError::TooLong);
-                    }
-                    total
-                })
-                .collect();
-            Ok(Color::Full(cparts[0]?, cparts[1]?, cparts[2]?))
+// decodes nroff style overstriking, `c\bc` is a bold `c` and `_\bc` an underlined one
+pub(crate) fn parse_overstrike(text: &str, state: &AnsiState) -> AnsiChain {
+    let characters: Vec<char> = text.chars().collect();
+    let mut chain: AnsiChain = Vec::new();
+    let mut index = 0;
+    while index < characters.len() {
+        let mut shown = characters[index];
+        let (mut bold, mut underline) = (false, false);
+        index += 1;
+        while characters.get(index) == Some(&'\x08') && index + 1 < characters.len() {
+            let struck = characters[index + 1];
+            if struck == shown {
+                bold = true;
+            } else if shown == '_' {
+                underline = true;
+                shown = struck;
+            } else if struck == '_' {
+                underline = true;
+            } else {
+                shown = struck;
+            }
+            index += 2;
         }
+        let cell_state = state.overstruck(bold, underline);
+        match chain.last_mut() {
+            Some((last_state, last_text)) if *last_state == cell_state => last_text.push(shown),
+            _ => chain.push((cell_state, shown.to_string())),
+        }
+    }
+    chain
+}
+
+// the colon separated form carries its arguments as sub parameters, the semicolon separated
+// form takes them from the parameters following it
+pub(crate) fn parse_color_parameters<'a>(
+    sub_parameters: Option<&str>,
+    parameters: &mut impl Iterator<Item = &'a str>,
+) -> Result<Color, AnsiError> {
+    if let Some(sub_parameters) = sub_parameters {
+        return parse_color_code(sub_parameters);
+    }
+    match parameters.next() {
+        Some("5") => palette_color(parameters.next().unwrap_or("")),
+        Some("2") => match [parameters.next(), parameters.next(), parameters.next()] {
+            [Some(r), Some(g), Some(b)] => full_color([r, g, b]),
+            _ => Err(AnsiError::InvalidFormat),
+        },
         _ => Err(AnsiError::InvalidFormat),
     }
 }
 
+// a color component or palette slot, at most three digits
+fn parse_component(split: &str) -> Result<u8, AnsiError> {
+    let (total, length) = parse_number(&mut split.bytes());
+    if length > 3 {
+        return Err(AnsiError::TooLong);
+    }
+    total
+}
+
+// the components after an optional color space id, which is ignored
+fn parse_sub_parameters(part: &str, components: usize) -> Result<[u8; 4], AnsiError> {
+    let count = part.split(':').count();
+    if count != components && count != components + 1 {
+        return Err(AnsiError::InvalidFormat);
+    }
+    let mut splits = part.split(':');
+    if count == components + 1 {
+        let color_space = splits.next().unwrap_or("");
+        if !color_space.is_empty() {
+            parse_component(color_space)?;
+        }
+    }
+    let mut values = [0; 4];
+    for (value, split) in values.iter_mut().zip(splits) {
+        *value = parse_component(split)?;
+    }
+    Ok(values)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -87,21 +287,140 @@ mod tests {
     #[case(200, 100, 50)]
     #[case(29, 99, 91)]
     fn full_color_from_extra_escape_part(#[case] r: u8, #[case] g: u8, #[case] b: u8) {
-        let result = parse_color_code(&mut format!("2;{r};{g};{b}m").chars());
+        let result = parse_color_code(&format!("2;{r};{g};{b}"));
         assert_eq!(result, Ok(Color::Full(r, g, b)));
     }
 
     #[rstest]
-    #[case("3;0;0;0m", AnsiError::InvalidFormat)]
-    #[case("2;256;0;0m", AnsiError::NumberParse)]
-    #[case("2;000;0000;128m", AnsiError::TooLong)]
-    #[case("2;0255;0128;0001m", AnsiError::TooLong)]
-    #[case("2;1;128;1;100m", AnsiError::InvalidFormat)]
-    #[case("2;011;300m", AnsiError::InvalidFormat)]
-    #[case("5;0112m", AnsiError::TooLong)]
-    #[case("5;1;1m", AnsiError::InvalidFormat)]
+    #[case("2::1:2:3", Color::Full(1, 2, 3))]
+    #[case("2:0:255:128:0", Color::Full(255, 128, 0))]
+    #[case("2:1:2:3", Color::Full(1, 2, 3))]
+    #[case("5:3", Color::Yellow)]
+    #[case("5:208", Color::Byte(208))]
+    #[case("3::0:255:55", Color::Full(255, 0, 200))]
+    #[case("3:255:0:255", Color::Full(0, 255, 0))]
+    #[case("4::0:0:0:0", Color::Full(255, 255, 255))]
+    #[case("4::255:0:0:51", Color::Full(0, 204, 204))]
+    #[case("4:0:0:255:0:255", Color::Full(0, 0, 0))]
+    fn color_from_colon_sub_parameters(#[case] str: &str, #[case] color: Color) {
+        let result = parse_color_code(str);
+        assert_eq!(result, Ok(color))
+    }
+
+    #[rstest]
+    #[case("[#{", Some(SgrStackOperation::Push(vec![])))]
+    #[case("[#p", Some(SgrStackOperation::Push(vec![])))]
+    #[case("[1;30;31#{", Some(SgrStackOperation::Push(vec![1, 30, 31])))]
+    #[case("[;4;;999#{", Some(SgrStackOperation::Push(vec![4])))]
+    #[case("[#}", Some(SgrStackOperation::Pop))]
+    #[case("[#q", Some(SgrStackOperation::Pop))]
+    #[case("[1#}", None)]
+    #[case("[1m", None)]
+    #[case("[#m", None)]
+    #[case("(0", None)]
+    fn sgr_stack_operations(#[case] str: &str, #[case] operation: Option<SgrStackOperation>) {
+        assert_eq!(parse_sgr_stack_operation(&mut str.chars()), operation)
+    }
+
+    #[rstest]
+    #[case("(0", Some((0, Charset::DecSpecialGraphics)))]
+    #[case("(B", Some((0, Charset::Ascii)))]
+    #[case(")0", Some((1, Charset::DecSpecialGraphics)))]
+    #[case(")A", Some((1, Charset::Ascii)))]
+    #[case("(", None)]
+    #[case("[0m", None)]
+    fn charset_designations(#[case] str: &str, #[case] designation: Option<(usize, Charset)>) {
+        assert_eq!(parse_charset_designation(&mut str.chars()), designation)
+    }
+
+    #[rstest]
+    #[case("]0;title\x07rest", Ok(()), "rest")]
+    #[case("]8;;https://example.com\x1b\\link", Ok(()), "link")]
+    #[case("Pq#0;2;0;0;0\x1b\\rest", Ok(()), "rest")]
+    #[case("\\rest", Ok(()), "rest")]
+    #[case("]0;unterminated", Err(AnsiError::InvalidFormat), "")]
+    #[case("[0m", Err(AnsiError::InvalidStartBrace), "0m")]
+    fn control_strings(
+        #[case] str: &str,
+        #[case] result: Result<(), AnsiError>,
+        #[case] rest: &str,
+    ) {
+        let mut part = str.chars();
+        assert_eq!(skip_control_string(&mut part), result);
+        assert_eq!(part.as_str(), rest);
+    }
+
+    #[rstest]
+    #[case("\u{9b}1m", "\x1b[1m")]
+    #[case("\u{9d}0;title\u{9c}", "\x1b]0;title\x1b\\")]
+    #[case("\u{90}q\u{9c}", "\x1bPq\x1b\\")]
+    #[case("no controls ÿ\u{a0}", "no controls ÿ\u{a0}")]
+    fn c1_controls_to_7_bit(#[case] str: &str, #[case] correct: &str) {
+        assert_eq!(C1Controls::new(str.chars()).collect::<String>(), correct)
+    }
+
+    #[test]
+    fn overstrike_decoding() {
+        let state = AnsiState::default();
+        let bold = state.overstruck(true, false);
+        let underlined = state.overstruck(false, true);
+        let both = state.overstruck(true, true);
+        assert_eq!(
+            parse_overstrike(
+                "N\x08NA\x08AM\x08ME\x08E ls _\x08f_\x08i_\x08l_\x08e _\x08x\x08x, a\x08_\x08",
+                &state
+            ),
+            vec![
+                (bold, "NAME".to_string()),
+                (state.clone(), " ls ".to_string()),
+                (underlined.clone(), "file".to_string()),
+                (state.clone(), " ".to_string()),
+                (both, "x".to_string()),
+                (state.clone(), ", ".to_string()),
+                (underlined, "a".to_string()),
+                (state.clone(), "\x08".to_string()),
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case(None, "5;208;1", Ok(Color::Byte(208)), Some("1"))]
+    #[case(None, "2;1;2;3;4", Ok(Color::Full(1, 2, 3)), Some("4"))]
+    #[case(Some("2::1:2:3"), "4", Ok(Color::Full(1, 2, 3)), Some("4"))]
+    #[case(None, "2;1;2", Err(AnsiError::InvalidFormat), None)]
+    #[case(None, "3;1;2;3", Err(AnsiError::InvalidFormat), Some("1"))]
+    fn color_from_parameters(
+        #[case] sub_parameters: Option<&str>,
+        #[case] parameters: &str,
+        #[case] color: Result<Color, AnsiError>,
+        #[case] next: Option<&str>,
+    ) {
+        let mut parameters = parameters.split(';');
+        assert_eq!(
+            parse_color_parameters(sub_parameters, &mut parameters),
+            color
+        );
+        assert_eq!(parameters.next(), next);
+    }
+
+    #[rstest]
+    #[case("3;0;0;0", AnsiError::InvalidFormat)]
+    #[case("2;256;0;0", AnsiError::NumberParse)]
+    #[case("2;000;0000;128", AnsiError::TooLong)]
+    #[case("2;0255;0128;0001", AnsiError::TooLong)]
+    #[case("2;1;128;1;100", AnsiError::InvalidFormat)]
+    #[case("2;011;300", AnsiError::InvalidFormat)]
+    #[case("5;0112", AnsiError::TooLong)]
+    #[case("5;1;1", AnsiError::InvalidFormat)]
+    #[case("2:1:2", AnsiError::InvalidFormat)]
+    #[case("2::1:2:3:4", AnsiError::InvalidFormat)]
+    #[case("2:x:1:2:3", AnsiError::InvalidFormat)]
+    #[case("2::1:256:3", AnsiError::NumberParse)]
+    #[case("2::1:0002:3", AnsiError::TooLong)]
+    #[case("4::1:2", AnsiError::InvalidFormat)]
+    #[case("2:1;2;3", AnsiError::InvalidFormat)]
     fn color_from_invalid_errors(#[case] str: &str, #[case] error_type: AnsiError) {
-        let result = parse_color_code(&mut str.chars());
+        let result = parse_color_code(str);
         assert_eq!(result, Err(error_type))
     }
 }
@@ -0,0 +1,308 @@
+use crate::{
+    color::Color,
+    state::{AnsiState, Intensity, Italics, StrikeThrough, Underline},
+    AnsiChain,
+};
+
+const TAB_WIDTH: usize = 8;
+
+pub struct SvgRenderer {
+    font_family: String,
+    font_size: f32,
+    cell_width: f32,
+    cell_height: f32,
+    padding: f32,
+    window_chrome: bool,
+    foreground: String,
+    background: String,
+}
+
+impl Default for SvgRenderer {
+    fn default() -> Self {
+        SvgRenderer {
+            font_family: "ui-monospace, Menlo, Consolas, monospace".to_string(),
+            font_size: 14.0,
+            cell_width: 8.4,
+            cell_height: 18.0,
+            padding: 10.0,
+            window_chrome: false,
+            foreground: "#D0D0D0".to_string(),
+            background: "#1C1C1C".to_string(),
+        }
+    }
+}
+
+impl SvgRenderer {
+    pub fn font(mut self, font_family: &str, font_size: f32) -> Self {
+        self.font_family = font_family.to_string();
+        self.font_size = font_size;
+        self
+    }
+
+    pub fn cell_size(mut self, cell_width: f32, cell_height: f32) -> Self {
+        self.cell_width = cell_width;
+        self.cell_height = cell_height;
+        self
+    }
+
+    pub fn padding(mut self, padding: f32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    // draw a title bar with the three window buttons above the text
+    pub fn window_chrome(mut self, window_chrome: bool) -> Self {
+        self.window_chrome = window_chrome;
+        self
+    }
+
+    pub fn colors(mut self, foreground: &str, background: &str) -> Self {
+        self.foreground = foreground.to_string();
+        self.background = background.to_string();
+        self
+    }
+
+    pub fn render(&self, chain: &AnsiChain) -> String {
+        let chrome_height = if self.window_chrome {
+            2.0 * self.cell_height
+        } else {
+            0.0
+        };
+        let top = self.padding + chrome_height;
+
+        // the same grid as `PngRenderer`, later text on a row draws over what was there
+        let mut grid: Vec<Vec<(char, &AnsiState)>> = vec![Vec::new()];
+        let mut column = 0;
+        for (state, text) in chain {
+            for c in text.chars() {
+                let row = grid.last_mut().unwrap();
+                match c {
+                    '\n' => {
+                        grid.push(Vec::new());
+                        column = 0;
+                    }
+                    '\r' => column = 0,
+                    '\t' => {
+                        column = (column / TAB_WIDTH + 1) * TAB_WIDTH;
+                        while row.len() < column {
+                            row.push((' ', state));
+                        }
+                    }
+                    c => {
+                        match row.get_mut(column) {
+                            Some(cell) => *cell = (c, state),
+                            None => row.push((c, state)),
+                        }
+                        column += 1;
+                    }
+                }
+            }
+        }
+        // a trailing newline doesn't start a visible row
+        if grid.len() > 1 && grid.last().is_some_and(|row| row.is_empty()) {
+            grid.pop();
+        }
+
+        let mut rows = Vec::new();
+        let mut backgrounds = String::new();
+        for (index, cells) in grid.iter().enumerate() {
+            let mut row = String::new();
+            let mut column = 0;
+            for run in cells.chunk_by(|(_, a), (_, b)| a == b) {
+                let state = run[0].1;
+                let (foreground, background) = self.colors_of(state);
+                let x = rounded(self.padding + column as f32 * self.cell_width);
+                let width = rounded(run.len() as f32 * self.cell_width);
+                if let Some(background) = &background {
+                    backgrounds.push_str(&format!(
+                        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                        x,
+                        rounded(top + index as f32 * self.cell_height),
+                        width,
+                        self.cell_height,
+                        html_escape::encode_double_quoted_attribute(background)
+                    ));
+                }
+                let segment: String = run.iter().map(|(c, _)| c).collect();
+                if !segment.trim().is_empty() {
+                    row.push_str(&format!(
+                        "<tspan x=\"{}\" textLength=\"{}\" lengthAdjust=\"spacingAndGlyphs\"{}>{}</tspan>",
+                        x,
+                        width,
+                        self.attributes_of(state, &foreground),
+                        html_escape::encode_text(&segment)
+                    ));
+                }
+                column += run.len();
+            }
+            rows.push(row);
+        }
+        let columns = grid.iter().map(|row| row.len()).max().unwrap_or(0);
+
+        let width = rounded(columns as f32 * self.cell_width + 2.0 * self.padding);
+        let height =
+            rounded(rows.len() as f32 * self.cell_height + 2.0 * self.padding + chrome_height);
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">",
+            width, height
+        );
+        svg.push_str(&format!(
+            "<rect width=\"{}\" height=\"{}\" rx=\"{}\" fill=\"{}\"/>",
+            width,
+            height,
+            if self.window_chrome { 6.0 } else { 0.0 },
+            html_escape::encode_double_quoted_attribute(&self.background)
+        ));
+        if self.window_chrome {
+            for (index, color) in ["#FF5F56", "#FFBD2E", "#27C93F"].iter().enumerate() {
+                svg.push_str(&format!(
+                    "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/>",
+                    self.padding + 6.0 + index as f32 * 20.0,
+                    self.padding + 6.0,
+                    6.0,
+                    color
+                ));
+            }
+        }
+        svg.push_str(&backgrounds);
+        svg.push_str(&format!(
+            "<g font-family=\"{}\" font-size=\"{}\" fill=\"{}\">",
+            html_escape::encode_double_quoted_attribute(&self.font_family),
+            self.font_size,
+            html_escape::encode_double_quoted_attribute(&self.foreground)
+        ));
+        for (index, row) in rows.iter().enumerate() {
+            if row.is_empty() {
+                continue;
+            }
+            svg.push_str(&format!(
+                "<text y=\"{}\" xml:space=\"preserve\">{}</text>",
+                rounded(top + (index as f32 + 0.75) * self.cell_height),
+                row
+            ));
+        }
+        svg.push_str("</g></svg>");
+        svg
+    }
+
+    // the foreground if it differs from the default, and the background if there is one
+    fn colors_of(&self, state: &AnsiState) -> (Option<String>, Option<String>) {
+        state.effective_colors(
+            |color| (*color != Color::None).then(|| color.to_string()),
+            self.foreground.clone(),
+            self.background.clone(),
+        )
+    }
+
+    fn attributes_of(&self, state: &AnsiState, foreground: &Option<String>) -> String {
+        let mut attributes = String::new();
+        if let Some(foreground) = foreground {
+            attributes.push_str(&format!(
+                " fill=\"{}\"",
+                html_escape::encode_double_quoted_attribute(foreground)
+            ));
+        }
+        match state.intensity {
+            Intensity::Normal => {}
+            Intensity::Bold => attributes.push_str(" font-weight=\"bold\""),
+            Intensity::Faint => attributes.push_str(" fill-opacity=\"0.5\""),
+        }
+        if state.italic == Italics::Yes {
+            attributes.push_str(" font-style=\"italic\"");
+        }
+        let mut lines = Vec::new();
+        if state.underline != Underline::None {
+            lines.push("underline");
+        }
+        if state.strikethrough == StrikeThrough::Yes {
+            lines.push("line-through");
+        }
+        if !lines.is_empty() {
+            attributes.push_str(&format!(" text-decoration=\"{}\"", lines.join(" ")));
+        }
+        attributes
+    }
+}
+
+// keeps float noise like 25.199999 out of the output
+fn rounded(n: f32) -> f32 {
+    (n * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_chain_on_grid() {
+        let chain = vec![
+            (AnsiState::default(), "ab ".to_string()),
+            (AnsiState::from_code("[1;31;44m"), "<c>\n".to_string()),
+            (AnsiState::from_code("[7m"), "d".to_string()),
+        ];
+        let svg = SvgRenderer::default()
+            .font("monospace", 10.0)
+            .cell_size(5.0, 10.0)
+            .padding(2.0)
+            .colors("white", "black")
+            .render(&chain);
+        assert_eq!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"34\" height=\"24\" viewBox=\"0 0 34 24\">\
+            <rect width=\"34\" height=\"24\" rx=\"0\" fill=\"black\"/>\
+            <rect x=\"17\" y=\"2\" width=\"15\" height=\"10\" fill=\"blue\"/>\
+            <rect x=\"2\" y=\"12\" width=\"5\" height=\"10\" fill=\"white\"/>\
+            <g font-family=\"monospace\" font-size=\"10\" fill=\"white\">\
+            <text y=\"9.5\" xml:space=\"preserve\">\
+            <tspan x=\"2\" textLength=\"15\" lengthAdjust=\"spacingAndGlyphs\">ab </tspan>\
+            <tspan x=\"17\" textLength=\"15\" lengthAdjust=\"spacingAndGlyphs\" fill=\"red\" font-weight=\"bold\">&lt;c&gt;</tspan></text>\
+            <text y=\"19.5\" xml:space=\"preserve\"><tspan x=\"2\" textLength=\"5\" lengthAdjust=\"spacingAndGlyphs\" fill=\"black\">d</tspan></text>\
+            </g></svg>"
+        );
+    }
+
+    #[test]
+    fn escape_colors_of_reversed_text() {
+        let chain = vec![(AnsiState::from_code("[7m"), "x".to_string())];
+        let svg = SvgRenderer::default()
+            .colors("#fff\"/>", "a\"b")
+            .render(&chain);
+        assert!(svg.contains(
+            "<rect x=\"10\" y=\"10\" width=\"8.4\" height=\"18\" fill=\"#fff&quot;/&gt;\"/>"
+        ));
+        assert!(svg.contains(" fill=\"a&quot;b\">x</tspan>"));
+    }
+
+    #[test]
+    fn expand_tabs_and_return_carriage() {
+        let render = |text: &str| {
+            SvgRenderer::default()
+                .cell_size(5.0, 10.0)
+                .padding(0.0)
+                .render(&vec![(AnsiState::default(), text.to_string())])
+        };
+        assert!(render("a\tb").contains(
+            "<tspan x=\"0\" textLength=\"45\" lengthAdjust=\"spacingAndGlyphs\">a       b</tspan>"
+        ));
+        assert_eq!(
+            render("progress 10%\rprogress 100%\r\n"),
+            render("progress 100%")
+        );
+        assert_eq!(render("abc\rx"), render("xbc"));
+    }
+
+    #[test]
+    fn window_chrome_makes_room() {
+        let chain = vec![(AnsiState::default(), "x\n".to_string())];
+        let svg = SvgRenderer::default()
+            .cell_size(10.0, 20.0)
+            .padding(10.0)
+            .window_chrome(true)
+            .render(&chain);
+        assert!(
+            svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"30\" height=\"80\"")
+        );
+        assert_eq!(svg.matches("<circle").count(), 3);
+        assert!(svg.contains("<text y=\"65\""));
+    }
+}
@@ -0,0 +1,14 @@
+use crate::Parser;
+
+mod log;
+
+pub(crate) use log::log;
+
+// runs split at different places look the same, so html is compared after reading it back
+pub(crate) fn merged(html: String) -> String {
+    let parser = Parser {
+        ansi_chain: crate::parse_html(&html).unwrap(),
+        ..Default::default()
+    };
+    parser.into_html()
+}
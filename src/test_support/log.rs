@@ -0,0 +1,17 @@
+// a log that touches most of what the parser keeps track of: colors and pushed styles and
+// charsets that carry over line ends, control strings, overstrike, C1 controls and markup characters
+pub fn log(count: usize) -> String {
+    let mut log = String::new();
+    for line in 0..count {
+        log.push_str(&match line % 7 {
+            0 => format!("\x1b[3{}mline {} starts red\n", line % 8, line),
+            1 => format!("continues in the same color {}\n", line),
+            2 => "\x1b[#{\x1b[1;4mpushed\x1b]0;a title\x07 still pushed\n".to_string(),
+            3 => "\x1b(0lqqk\x0e\nxx\x0f\x1b(B box\x1b[#} popped\n".to_string(),
+            4 => "N\x08NA\x08AM\x08ME\x08E \x1b[0m_\x08u\n\x08_x\n".to_string(),
+            5 => "\x1b[38;2;1;2;3m\x1b[48;5;200m<&>\x1b[0m\x1b[0m\n\n".to_string(),
+            _ => "\u{e9}\u{9b}1mc1 bold\u{9b}0m\n".to_string(),
+        });
+    }
+    log
+}
@@ -0,0 +1,49 @@
+#include <assert.h>
+#include <stdio.h>
+#include <string.h>
+
+#include "anstml.h"
+
+static int equals(AnstmlBuffer buffer, const char *expected) {
+    int same = buffer.len == strlen(expected) && memcmp(buffer.data, expected, buffer.len) == 0;
+    if (!same) {
+        fprintf(stderr, "expected %s, got %.*s\n", expected, (int)buffer.len, buffer.data);
+    }
+    anstml_buffer_free(buffer);
+    return same;
+}
+
+int main(void) {
+    const char *text = "a \x1b[1mbold\x1b[0m <b>";
+    AnstmlBuffer out;
+    assert(anstml_convert((const uint8_t *)text, strlen(text), NULL, &out) == ANSTML_STATUS_OK);
+    assert(equals(out, "<pre>a <span style=\"font-weight:bold;\">bold</span> &lt;b&gt;</pre>"));
+
+    AnstmlOptions options = {.decode_overstrike = true, .c1_controls = false};
+    AnstmlConverter *converter = anstml_converter_new(&options);
+    assert(anstml_converter_feed(converter, (const uint8_t *)"N\bN \x1b[3", 7, &out) == ANSTML_STATUS_OK);
+    assert(equals(out, "<pre><span style=\"font-weight:bold;\">N</span> "));
+    assert(anstml_converter_feed(converter, (const uint8_t *)"1m\xc3", 3, &out) == ANSTML_STATUS_OK);
+    assert(equals(out, ""));
+    assert(anstml_converter_feed(converter, (const uint8_t *)"\xa9", 1, &out) == ANSTML_STATUS_OK);
+    assert(equals(out, "<span style=\"color:red;\">\xc3\xa9</span>"));
+    assert(anstml_converter_finish(converter, &out) == ANSTML_STATUS_OK);
+    assert(equals(out, "</pre>"));
+
+    const char *concealed = "\x1b[8mhidden\x1b[53m\x1b[2J";
+    assert(anstml_convert((const uint8_t *)concealed, strlen(concealed), NULL, &out) == ANSTML_STATUS_OK);
+    assert(equals(out, "<pre>hidden</pre>"));
+
+    options.c1_controls = true;
+    const char *raw_c1 = "\x9b" "1mbold\x9b" "0m";
+    assert(anstml_convert((const uint8_t *)raw_c1, strlen(raw_c1), &options, &out) == ANSTML_STATUS_OK);
+    assert(equals(out, "<pre><span style=\"font-weight:bold;\">bold</span></pre>"));
+
+    converter = anstml_converter_new(NULL);
+    assert(anstml_converter_feed(converter, (const uint8_t *)"\x1b[1:3mx", 7, &out) == ANSTML_STATUS_INVALID_FORMAT);
+    assert(anstml_converter_feed(NULL, (const uint8_t *)"x", 1, &out) == ANSTML_STATUS_NULL_POINTER);
+    anstml_converter_free(converter);
+
+    puts("anstml C API ok");
+    return 0;
+}
@@ -0,0 +1,67 @@
+// builds tests/c/anstml_test.c against the cdylib and the generated header, and runs it,
+// and checks that the header still matches src/ffi.rs
+#![cfg(feature = "ffi")]
+
+use std::{env, path::PathBuf, process::Command};
+
+#[test]
+fn c_api() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    // the crate isn't a cdylib by default so it stays usable without std, and the main target
+    // directory is locked while tests run, so the library is built into its own
+    let target = root.join("target").join("c-api");
+    let status = Command::new(env!("CARGO"))
+        .args(["rustc", "--lib", "--crate-type", "cdylib"])
+        .args(["--no-default-features", "--features", "ffi"])
+        .arg("--target-dir")
+        .arg(&target)
+        .current_dir(&root)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let library = target.join("debug");
+    let binary = library.join("anstml_test");
+    let status = Command::new(env::var("CC").unwrap_or_else(|_| "cc".to_string()))
+        .args(["-std=c99", "-Wall", "-Wextra", "-Werror", "-I"])
+        .arg(root.join("include"))
+        .arg(root.join("tests/c/anstml_test.c"))
+        .arg("-L")
+        .arg(&library)
+        .args(["-lanstml", "-o"])
+        .arg(&binary)
+        .status()
+        .expect("a C compiler is needed for this test");
+    assert!(status.success());
+    let output = Command::new(&binary)
+        .env("LD_LIBRARY_PATH", &library)
+        .env("DYLD_LIBRARY_PATH", &library)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(output.stdout, b"anstml C API ok\n");
+}
+
+#[test]
+fn header_is_current() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let status = Command::new(env::var("CBINDGEN").unwrap_or_else(|_| "cbindgen".to_string()))
+        .args([
+            "--config",
+            "cbindgen.toml",
+            "--output",
+            "include/anstml.h",
+            "--verify",
+        ])
+        .current_dir(&root)
+        .status()
+        .expect("cbindgen is needed for this test, `cargo install cbindgen`");
+    assert!(
+        status.success(),
+        "include/anstml.h differs from src/ffi.rs, regenerate it with \
+        `cbindgen --config cbindgen.toml --output include/anstml.h`"
+    );
+}